@@ -1,22 +1,57 @@
+use std::borrow::Cow;
+
 /// Markdown decoding warning kind
 #[non_exhaustive]
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub enum WarningKind {
     /// Depending on markdown flavor may be a heading, may not
     ///
-    /// `#Ambiguous Heading`
+    /// `#Ambiguous Heading` or `####### Ambiguous Heading`
     ///
     /// To disambiguate, use either
     ///
     ///  - `# My Heading`
     ///  - `\# Line that starts with a #`
     AmbiguousHeading,
+    /// Footnote reference without a matching definition in the document
+    ///
+    /// `Something[^undefined]`
+    UndefinedFootnote,
 }
 
 /// Markdown decoding warning
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct Warning<'a> {
-    line_text: &'a str,
+    line_text: Cow<'a, str>,
     line_number: u16,
     warning: WarningKind,
 }
+
+impl<'a> Warning<'a> {
+    pub(crate) fn new(
+        line_text: Cow<'a, str>,
+        line_number: u16,
+        warning: WarningKind,
+    ) -> Self {
+        Self {
+            line_text,
+            line_number,
+            warning,
+        }
+    }
+
+    /// Get the text of the line the warning is for.
+    pub fn line_text(&self) -> &str {
+        &self.line_text
+    }
+
+    /// Get the line number (starting at 1) the warning is for.
+    pub fn line_number(&self) -> u16 {
+        self.line_number
+    }
+
+    /// Get the kind of warning.
+    pub fn kind(&self) -> &WarningKind {
+        &self.warning
+    }
+}
@@ -0,0 +1,204 @@
+//! Autolinks within the inline markdown of a block, `<https://example.org>`
+//! and `<me@example.org>`, or bare `https://example.org`, `www.example.org`
+//! and `me@example.org`
+
+use std::{borrow::Cow, mem, ops::Range};
+
+use super::slice;
+use crate::Md;
+
+/// Prefixes of bare links
+const PREFIXES: [&str; 3] = ["https://", "http://", "www."];
+
+/// Return the length of an autolink `<https://example.org>` or
+/// `<me@example.org>` at the start of `text`.
+pub(super) fn angle(text: &str) -> Option<usize> {
+    let end = text.find('>')?;
+    let link = &text[1..end];
+
+    (uri(link) || email(link)).then_some(end + 1)
+}
+
+/// Decode bare links in the text events of a line.
+pub(super) fn decode(mds: &mut Vec<Md<'_>>) {
+    let bare = mds.iter().any(|md| match md {
+        Md::Text(text) => {
+            text.contains('@') || PREFIXES.iter().any(|p| text.contains(p))
+        }
+        _ => false,
+    });
+
+    if !bare {
+        return;
+    }
+
+    for md in mem::take(mds) {
+        match md {
+            Md::Text(text) => split(text, mds),
+            md => mds.push(md),
+        }
+    }
+}
+
+/// Split text into text and bare links.
+fn split<'a>(text: Cow<'a, str>, mds: &mut Vec<Md<'a>>) {
+    let mut start = 0;
+    let mut index = 0;
+
+    while let Some(c) = text[index..].chars().next() {
+        let link = if c == '@' {
+            bare_email(&text, start, index)
+        } else {
+            // Bare links start words, or follow emphasis or a parenthesis
+            let word = text[..index].chars().next_back().map_or(true, |c| {
+                c.is_whitespace() || matches!(c, '*' | '_' | '~' | '(')
+            });
+
+            word.then(|| bare_url(&text[index..]))
+                .flatten()
+                .map(|len| index..index + len)
+        };
+        let Some(link) = link else {
+            index += c.len_utf8();
+            continue;
+        };
+
+        if start < link.start {
+            mds.push(Md::Text(slice(&text, start..link.start)));
+        }
+        mds.push(Md::Link(slice(&text, link.clone())));
+        start = link.end;
+        index = link.end;
+    }
+
+    if start < text.len() || text.is_empty() {
+        mds.push(Md::Text(slice(&text, start..)));
+    }
+}
+
+/// Return true for an absolute URI, a scheme followed by `:` and no spaces,
+/// controls or angle brackets.
+fn uri(link: &str) -> bool {
+    let Some((scheme, rest)) = link.split_once(':') else {
+        return false;
+    };
+
+    (2..=32).contains(&scheme.len())
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.bytes().all(|b| {
+            b.is_ascii_alphanumeric() || matches!(b, b'+' | b'.' | b'-')
+        })
+        && !rest.contains(|c: char| {
+            c.is_ascii_whitespace() || c.is_ascii_control() || c == '<'
+        })
+}
+
+/// Return true for an email address.
+fn email(link: &str) -> bool {
+    let Some((local, domain)) = link.split_once('@') else {
+        return false;
+    };
+
+    !local.is_empty()
+        && local.bytes().all(|b| {
+            b.is_ascii_alphanumeric() || b"._!#$%&'*+/=?^`{|}~-".contains(&b)
+        })
+        && domain.split('.').all(|label| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        })
+}
+
+/// Return the length of a bare link starting with `https://`, `http://` or
+/// `www.` at the start of `text`, without trailing punctuation.
+fn bare_url(text: &str) -> Option<usize> {
+    let prefix = PREFIXES.iter().find(|p| text.starts_with(*p))?;
+    let end = text
+        .find(|c: char| c.is_whitespace() || c == '<')
+        .unwrap_or(text.len());
+    let len = trim(&text[..end]);
+    // The domain of `www.` links includes it
+    let domain = match *prefix {
+        "www." => &text[..len],
+        _ => &text[prefix.len()..len],
+    };
+
+    domain_valid(domain).then_some(len)
+}
+
+/// Return the range of a bare email address with `@` at byte offset `at`,
+/// starting no earlier than byte offset `start`.
+fn bare_email(text: &str, start: usize, at: usize) -> Option<Range<usize>> {
+    let local = start
+        + text[start..at]
+            .trim_end_matches(|c: char| {
+                c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+')
+            })
+            .len();
+    let rest = &text[at + 1..];
+    let len = rest
+        .find(|c: char| {
+            !(c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+        })
+        .unwrap_or(rest.len());
+    let domain = rest[..len].trim_end_matches('.');
+    let valid = local < at
+        && domain.contains('.')
+        && !domain.ends_with(['-', '_'])
+        && domain.split('.').all(|label| !label.is_empty());
+
+    valid.then(|| local..at + 1 + domain.len())
+}
+
+/// Return true if the link starts with a domain, labels separated by
+/// periods, with no underscores in the last two.
+fn domain_valid(link: &str) -> bool {
+    let len = link
+        .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '.')))
+        .unwrap_or(link.len());
+    let domain = &link[..len];
+    let mut labels = domain.rsplit('.');
+
+    domain.contains('.')
+        && labels
+            .by_ref()
+            .take(2)
+            .all(|label| !label.is_empty() && !label.contains('_'))
+        && labels.all(|label| !label.is_empty())
+}
+
+/// Return the length of a link without trailing punctuation, unmatched `)`
+/// or entity references.
+fn trim(link: &str) -> usize {
+    let mut link = link;
+
+    loop {
+        if let Some(rest) =
+            link.strip_suffix(['?', '!', '.', ',', ':', '*', '_', '~'])
+        {
+            link = rest;
+        } else if link.ends_with(')')
+            && link.matches(')').count() > link.matches('(').count()
+        {
+            link = &link[..link.len() - 1];
+        } else if let Some(entity) = entity(link) {
+            link = &link[..entity];
+        } else {
+            return link.len();
+        }
+    }
+}
+
+/// Return the byte offset of an entity reference `&name;` ending the link.
+fn entity(link: &str) -> Option<usize> {
+    let name = link.strip_suffix(';')?;
+    let amp = name.rfind('&')?;
+    let name = &name[amp + 1..];
+
+    (!name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric()))
+        .then_some(amp)
+}
@@ -0,0 +1,286 @@
+//! Emphasis and other styles within the inline markdown of a block, following
+//! the CommonMark delimiter run rules
+
+use std::borrow::Cow;
+
+use super::slice;
+use crate::Md;
+
+/// Characters of delimiter runs
+const MARKERS: [char; 6] = ['*', '_', '~', '=', '^', '-'];
+
+/// Inline event, or run of delimiter characters
+enum Inline<'a> {
+    Md(Md<'a>),
+    Run(Run<'a>),
+}
+
+/// Run of delimiter characters that may open or close a style
+struct Run<'a> {
+    marker: char,
+    /// Number of characters in the run
+    len: usize,
+    /// Number of characters not matched yet, written as text
+    left: usize,
+    can_open: bool,
+    can_close: bool,
+    /// Events closing styles, innermost first
+    closes: Vec<Md<'a>>,
+    /// Events opening styles, outermost first
+    opens: Vec<Md<'a>>,
+}
+
+/// Decode emphasis and other styles in the events of each line of a block.
+pub(super) fn decode<'a>(lines: Vec<Vec<Md<'a>>>) -> Vec<Vec<Md<'a>>> {
+    let markers = lines.iter().flatten().any(|md| match md {
+        Md::Text(text) => text.contains(MARKERS),
+        _ => false,
+    });
+
+    if !markers {
+        return lines;
+    }
+
+    let count = lines.len();
+    let mut inlines = Vec::new();
+
+    for (line, mds) in lines.into_iter().enumerate() {
+        let neighbors: Vec<char> = mds.iter().map(neighbor).collect();
+
+        for (index, md) in mds.into_iter().enumerate() {
+            let Md::Text(text) = md else {
+                inlines.push((line, Inline::Md(md)));
+                continue;
+            };
+            let before = index.checked_sub(1).map_or(' ', |i| neighbors[i]);
+            let after = neighbors.get(index + 1).copied().unwrap_or(' ');
+
+            split(text, before, after, line, &mut inlines);
+        }
+    }
+
+    process(&mut inlines);
+
+    let mut lines: Vec<Vec<Md<'a>>> = (0..count).map(|_| Vec::new()).collect();
+
+    for (line, inline) in inlines {
+        let mds = &mut lines[line];
+
+        match inline {
+            Inline::Md(Md::Text(text)) => push_text(mds, text),
+            Inline::Md(md) => mds.push(md),
+            Inline::Run(run) => {
+                mds.extend(run.closes);
+                if run.left > 0 {
+                    let text = run.marker.to_string().repeat(run.left);
+
+                    push_text(mds, Cow::Owned(text));
+                }
+                mds.extend(run.opens);
+            }
+        }
+    }
+
+    lines
+}
+
+/// Return the character standing in for an event next to text, whitespace
+/// for line breaks and punctuation for others.
+fn neighbor(md: &Md<'_>) -> char {
+    match md {
+        Md::LineBreak => ' ',
+        _ => '!',
+    }
+}
+
+/// Split text into text and delimiter runs, with `before` and `after` the
+/// characters next to the text (whitespace at the start or end of the line).
+fn split<'a>(
+    text: Cow<'a, str>,
+    before: char,
+    after: char,
+    line: usize,
+    inlines: &mut Vec<(usize, Inline<'a>)>,
+) {
+    let mut start = 0;
+    let mut index = 0;
+
+    while let Some(offset) = text[index..].find(MARKERS) {
+        let run = index + offset;
+        let marker = text[run..].chars().next().unwrap_or_default();
+        let escapes = run - text[..run].trim_end_matches('\\').len();
+
+        // Backslash escaped delimiters are text
+        if escapes % 2 == 1 {
+            index = run + 1;
+            continue;
+        }
+
+        let len = text[run..].chars().take_while(|&c| c == marker).count();
+        let end = run + len;
+        let prev = text[..run].chars().next_back().unwrap_or(before);
+        let next = text[end..].chars().next().unwrap_or(after);
+        let left = !next.is_whitespace()
+            && (!is_punctuation(next)
+                || prev.is_whitespace()
+                || is_punctuation(prev));
+        let right = !prev.is_whitespace()
+            && (!is_punctuation(prev)
+                || next.is_whitespace()
+                || is_punctuation(next));
+        // Intraword `_` and `--` don't open or close a style
+        let (can_open, can_close) = if matches!(marker, '_' | '-') {
+            (
+                left && (!right || is_punctuation(prev)),
+                right && (!left || is_punctuation(next)),
+            )
+        } else {
+            (left, right)
+        };
+
+        if start < run {
+            inlines
+                .push((line, Inline::Md(Md::Text(slice(&text, start..run)))));
+        }
+        inlines.push((
+            line,
+            Inline::Run(Run {
+                marker,
+                len,
+                left: len,
+                can_open,
+                can_close,
+                closes: Vec::new(),
+                opens: Vec::new(),
+            }),
+        ));
+        index = end;
+        start = end;
+    }
+
+    if start < text.len() || text.is_empty() {
+        inlines.push((line, Inline::Md(Md::Text(slice(&text, start..)))));
+    }
+}
+
+/// Match closing delimiter runs with the nearest opening delimiter runs
+/// before them, from the first closing run.
+fn process(inlines: &mut [(usize, Inline<'_>)]) {
+    let mut runs: Vec<usize> = inlines
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, inline))| matches!(inline, Inline::Run(_)))
+        .map(|(index, _)| index)
+        .collect();
+    let mut closer = 0;
+
+    while closer < runs.len() {
+        let (head, tail) = inlines.split_at_mut(runs[closer]);
+        let Inline::Run(close) = &mut tail[0].1 else {
+            unreachable!()
+        };
+
+        if !close.can_close || close.left == 0 {
+            closer += 1;
+            continue;
+        }
+
+        let opener = (0..closer).rev().find(|&opener| {
+            let Inline::Run(open) = &head[runs[opener]].1 else {
+                unreachable!()
+            };
+
+            matches(open, close)
+        });
+        let Some(opener) = opener else {
+            closer += 1;
+            continue;
+        };
+        let Inline::Run(open) = &mut head[runs[opener]].1 else {
+            unreachable!()
+        };
+        let used = if !matches!(close.marker, '*' | '_') {
+            close.len
+        } else if open.left >= 3 && close.left >= 3 {
+            3
+        } else if open.left >= 2 && close.left >= 2 {
+            2
+        } else {
+            1
+        };
+        let (start, end) = style(close.marker, used);
+
+        open.left -= used;
+        close.left -= used;
+        open.opens.insert(0, start);
+        close.closes.push(end);
+
+        let (open_done, close_done) = (open.left == 0, close.left == 0);
+
+        // Runs between can't match outside the style, and are text
+        runs.drain(opener + 1..closer);
+        closer = opener + 1;
+        if open_done {
+            runs.remove(opener);
+            closer -= 1;
+        }
+        if close_done {
+            runs.remove(closer);
+        }
+    }
+}
+
+/// Return true if the run `open` can open a style that the run `close`
+/// closes.
+fn matches(open: &Run<'_>, close: &Run<'_>) -> bool {
+    if open.marker != close.marker || !open.can_open || open.left == 0 {
+        return false;
+    }
+
+    // Other styles use whole runs of the same length, so `~` and `~~` differ
+    if !matches!(close.marker, '*' | '_') {
+        return open.len == close.len
+            && open.left == open.len
+            && matches!(
+                (close.marker, close.len),
+                ('~', 1 | 2) | ('=' | '-', 2) | ('^', 1),
+            );
+    }
+
+    let both = (open.can_open && open.can_close)
+        || (close.can_open && close.can_close);
+    // Rule of 3, so `*foo**bar*` isn't `<em>foo</em><em>bar</em>`
+    let multiple_of_3 = (open.len + close.len) % 3 == 0
+        && (open.len % 3 != 0 || close.len % 3 != 0);
+
+    !(both && multiple_of_3)
+}
+
+/// Return the events opening and closing a style using `used` characters
+/// `marker` from each run.
+fn style(marker: char, used: usize) -> (Md<'static>, Md<'static>) {
+    match (marker, used) {
+        ('*' | '_', 1) => (Md::Italic(true), Md::Italic(false)),
+        ('*' | '_', 2) => (Md::Bold(true), Md::Bold(false)),
+        ('*' | '_', _) => (Md::BoldItalic(true), Md::BoldItalic(false)),
+        ('~', 1) => (Md::Subscript(true), Md::Subscript(false)),
+        ('~', _) => (Md::Strikethrough(true), Md::Strikethrough(false)),
+        ('=', _) => (Md::Highlight(true), Md::Highlight(false)),
+        ('^', _) => (Md::Superscript(true), Md::Superscript(false)),
+        _ => (Md::Underline(true), Md::Underline(false)),
+    }
+}
+
+/// Add text to the events, joined with any text event ending them.
+fn push_text<'a>(mds: &mut Vec<Md<'a>>, text: Cow<'a, str>) {
+    match mds.last_mut() {
+        Some(Md::Text(last)) => last.to_mut().push_str(&text),
+        _ => mds.push(Md::Text(text)),
+    }
+}
+
+/// Return true for ASCII or Unicode punctuation and symbols.
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation()
+        || (!c.is_ascii() && !c.is_alphanumeric() && !c.is_whitespace())
+}
@@ -0,0 +1,609 @@
+//! Inline markdown within the text of a block
+
+use std::{borrow::Cow, iter, mem, ops::Range};
+
+use super::{autolink, emphasis, slice};
+use crate::Md;
+
+/// Decode the inline markdown of the lines of a block, with the events of
+/// each line.
+///
+/// Lines ending in two or more spaces or a `\` (except the last) end with a
+/// `LineBreak` event. Otherwise each line after the first starts with, and
+/// each line before the last ends with, a `Text` event (empty if needed), so
+/// that lines stay separated. Emphasis and other styles may span lines.
+///
+/// Code spans may span lines too, with each line ending within them as a
+/// space. The events of the lines they join are with the first, and the
+/// other lines have none.
+pub(super) fn decode<'a>(lines: Vec<Cow<'a, str>>) -> Vec<Vec<Md<'a>>> {
+    let (lines, joined): (Vec<_>, Vec<_>) =
+        join_code_spans(lines).into_iter().unzip();
+    let count = lines.len();
+    let lines = lines
+        .into_iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let mut mds = Vec::new();
+            let last = index + 1 == count;
+            let (line, breaks) = match hard_break(&line) {
+                Some(len) if !last => (slice(&line, ..len), true),
+                // Spaces ending the block aren't a line break
+                _ if last && line.ends_with(' ') => {
+                    (slice(&line, ..line.trim_end_matches(' ').len()), false)
+                }
+                _ => (line, false),
+            };
+
+            decode_line(line, &mut mds);
+            autolink::decode(&mut mds);
+            // A `<br>` may end the line already
+            if breaks && mds.last() != Some(&Md::LineBreak) {
+                mds.push(Md::LineBreak);
+            }
+            mds
+        })
+        .collect();
+    let mut after_break = true;
+
+    emphasis::decode(lines)
+        .into_iter()
+        .enumerate()
+        .map(|(index, mut mds)| {
+            if !after_break && !matches!(mds.first(), Some(Md::Text(_))) {
+                mds.insert(0, Md::Text(Cow::Borrowed("")));
+            }
+            after_break = mds.last() == Some(&Md::LineBreak);
+            if index + 1 < count
+                && !after_break
+                && !matches!(mds.last(), Some(Md::Text(_)))
+            {
+                mds.push(Md::Text(Cow::Borrowed("")));
+            }
+            mds
+        })
+        .zip(joined)
+        .flat_map(|(mds, joined)| {
+            iter::once(mds).chain((1..joined).map(|_| Vec::new()))
+        })
+        .collect()
+}
+
+/// Join each line with the following lines a code span starting on it
+/// continues on, with the line endings as spaces, returning the lines and the
+/// number of lines joined into each.
+fn join_code_spans(mut lines: Vec<Cow<'_, str>>) -> Vec<(Cow<'_, str>, usize)> {
+    let mut joined = Vec::new();
+    let mut next = 0;
+
+    while next < lines.len() {
+        let mut line = mem::take(&mut lines[next]);
+        let mut count = 1;
+        let mut index = 0;
+
+        next += 1;
+        while let Some(offset) = line[index..].find('`') {
+            let start = index + offset;
+            let escapes = start - line[..start].trim_end_matches('\\').len();
+
+            // Backslash escaped backticks are text
+            if escapes % 2 == 1 {
+                index = start + 1;
+                continue;
+            }
+
+            if let Some((_, len)) = code_span(&line[start..]) {
+                index = start + len;
+                continue;
+            }
+
+            let mut code = line[start..].to_string();
+            let mut more = 0;
+            let closed = lines[next..].iter().find_map(|text| {
+                code.push(' ');
+                code.push_str(text);
+                more += 1;
+                code_span(&code).map(|(_, len)| len)
+            });
+
+            // Unmatched backticks are text
+            let Some(len) = closed else {
+                index = start + backticks(&line[start..]);
+                continue;
+            };
+
+            line = Cow::Owned(format!("{}{code}", &line[..start]));
+            index = start + len;
+            next += more;
+            count += more;
+        }
+        joined.push((line, count));
+    }
+
+    joined
+}
+
+/// Return the length of the line before a hard line break ending it, two or
+/// more spaces or a `\`.
+fn hard_break(line: &str) -> Option<usize> {
+    let text = line.trim_end_matches(' ');
+
+    if line.len() - text.len() >= 2 {
+        return Some(text.len());
+    }
+
+    let text = line.strip_suffix('\\')?;
+    let escaped = text.len() - text.trim_end_matches('\\').len();
+
+    (escaped % 2 == 0).then_some(text.len())
+}
+
+/// Decode the inline markdown of one line.
+fn decode_line<'a>(line: Cow<'a, str>, mds: &mut Vec<Md<'a>>) {
+    let first = mds.len();
+    let mut text = 0;
+    let mut index = 0;
+
+    while let Some(offset) = line[index..].find(['[', '<', '`']) {
+        let start = index + offset;
+
+        if line[start..].starts_with('`') {
+            let escapes = start - line[..start].trim_end_matches('\\').len();
+
+            // Backslash escaped backticks are text
+            if escapes % 2 == 1 {
+                index = start + 1;
+                continue;
+            }
+
+            // Unmatched backticks are text
+            let Some((code, len)) = code_span(&line[start..]) else {
+                index = start + backticks(&line[start..]);
+                continue;
+            };
+
+            if text < start {
+                mds.push(Md::Text(slice(&line, text..start)));
+            }
+            mds.push(Md::Code(slice(
+                &line,
+                start + code.start..start + code.end,
+            )));
+            index = start + len;
+            text = index;
+            continue;
+        }
+
+        if line[start..].starts_with('<') {
+            let tag = br(&line[start..])
+                .map(|len| (Md::LineBreak, len))
+                .or_else(|| underline(&line[start..]))
+                .or_else(|| {
+                    let len = autolink::angle(&line[start..])?;
+
+                    Some((
+                        Md::Link(slice(&line, start + 1..start + len - 1)),
+                        len,
+                    ))
+                });
+            let Some((md, len)) = tag else {
+                index = start + 1;
+                continue;
+            };
+
+            if text < start {
+                mds.push(Md::Text(slice(&line, text..start)));
+            }
+            mds.push(md);
+            index = start + len;
+            text = index;
+            continue;
+        }
+
+        let footnote = line[start..].starts_with("[^");
+        let image = !footnote && line[..start].ends_with('!');
+        let parsed = if line[..start].ends_with('\\') {
+            None
+        } else if footnote {
+            footnote_ref(&line, start)
+        } else {
+            link(&line, start, image)
+        };
+        let Some((parsed, end)) = parsed else {
+            index = start + 1;
+            continue;
+        };
+        let start = start - usize::from(image);
+
+        if text < start {
+            mds.push(Md::Text(slice(&line, text..start)));
+        }
+        mds.extend(parsed);
+        index = end;
+        text = end;
+    }
+
+    if text < line.len() || mds.len() == first {
+        mds.push(Md::Text(slice(&line, text..)));
+    }
+}
+
+/// Return the range of the code and the length of a code span starting with
+/// a run of backticks at the start of `text`, closed by a run of the same
+/// length.
+///
+/// Code both starting and ending with a space (but not only spaces) has one
+/// stripped from each end.
+fn code_span(text: &str) -> Option<(Range<usize>, usize)> {
+    let len = backticks(text);
+    let mut index = len;
+
+    while let Some(offset) = text[index..].find('`') {
+        let start = index + offset;
+        let close = backticks(&text[start..]);
+
+        if close == len {
+            let code = &text[len..start];
+            let strip = code.starts_with(' ')
+                && code.ends_with(' ')
+                && !code.trim_matches(' ').is_empty();
+            let strip = usize::from(strip);
+
+            return Some((len + strip..start - strip, start + close));
+        }
+        index = start + close;
+    }
+
+    None
+}
+
+/// Return the length of the run of backticks at the start of `text`.
+fn backticks(text: &str) -> usize {
+    text.len() - text.trim_start_matches('`').len()
+}
+
+/// Return the length of a `<br>`, `<br/>` or `<br />` tag at the start of
+/// `text`.
+fn br(text: &str) -> Option<usize> {
+    let tag = text.get(..3)?;
+
+    if !tag.eq_ignore_ascii_case("<br") {
+        return None;
+    }
+
+    let rest = &text[3..];
+    let attrs = rest.trim_start_matches([' ', '\t']);
+    let attrs = attrs.strip_prefix('/').unwrap_or(attrs);
+
+    attrs
+        .starts_with('>')
+        .then_some(text.len() - attrs.len() + 1)
+}
+
+/// Return the event and the length of an `<ins>`, `<u>`, `</ins>` or `</u>`
+/// tag at the start of `text`.
+fn underline(text: &str) -> Option<(Md<'static>, usize)> {
+    let (open, rest) = match text.strip_prefix("</") {
+        Some(rest) => (false, rest),
+        None => (true, &text[1..]),
+    };
+    let name = ["ins>", "u>"].into_iter().find(|name| {
+        rest.get(..name.len())
+            .is_some_and(|tag| tag.eq_ignore_ascii_case(name))
+    })?;
+
+    Some((Md::Underline(open), text.len() - rest.len() + name.len()))
+}
+
+/// Return the events and the byte offset after a footnote reference starting
+/// at byte offset `start`.
+fn footnote_ref<'a>(
+    line: &Cow<'a, str>,
+    start: usize,
+) -> Option<(Vec<Md<'a>>, usize)> {
+    let label = start + 2;
+    let len = footnote_label(&line[label..])?;
+
+    Some((
+        vec![Md::FootnoteRef(slice(line, label..label + len))],
+        label + len + 1,
+    ))
+}
+
+/// Return the events and the byte offset after a link (or image, if `image`)
+/// with text starting with `[` at byte offset `start`.
+fn link<'a>(
+    line: &Cow<'a, str>,
+    start: usize,
+    image: bool,
+) -> Option<(Vec<Md<'a>>, usize)> {
+    let open = start + 1;
+    let close = open + closing_bracket(&line[open..])?;
+    let text = slice(line, open..close);
+    let after = close + 1;
+    let rest = &line[after..];
+
+    if text.trim().is_empty() {
+        return None;
+    }
+
+    let reference = |text| {
+        if image {
+            Md::ImageRef(text)
+        } else {
+            Md::LinkRef(text)
+        }
+    };
+
+    // Inline `[text](destination "title")`
+    if let Some(inline) = rest.strip_prefix('(') {
+        if let Some((destination, title, len)) = inline_link(inline) {
+            let offset = after + 1;
+            let mut mds = vec![
+                reference(text),
+                Md::LinkVal(slice(
+                    line,
+                    offset + destination.start..offset + destination.end,
+                )),
+            ];
+
+            if let Some(title) = title {
+                mds.push(Md::Title(slice(
+                    line,
+                    offset + title.start..offset + title.end,
+                )));
+            }
+
+            return Some((mds, offset + len));
+        }
+    }
+
+    // Full `[text][label]`, numbered `[text][1]` or collapsed `[text][]`
+    if let Some(label) = rest.strip_prefix('[') {
+        if let Some(len) = closing_bracket(label) {
+            let end = after + len + 2;
+            let label = &label[..len];
+            let start = after + 1 + label.len() - label.trim_start().len();
+            let label = label.trim();
+
+            if label.is_empty() {
+                return Some((vec![reference(text)], end));
+            }
+
+            let Ok(number) = label.parse() else {
+                let label = slice(line, start..start + label.len());
+
+                return Some((
+                    vec![reference(text), Md::LinkLabel(label)],
+                    end,
+                ));
+            };
+
+            return Some((
+                vec![if image {
+                    Md::ImageNum(text, number)
+                } else {
+                    Md::LinkNum(text, number)
+                }],
+                end,
+            ));
+        }
+    }
+
+    // Shortcut `[text]`, except for alert and footnote syntax
+    if text.starts_with(['!', '^']) {
+        return None;
+    }
+
+    Some((vec![reference(text)], after))
+}
+
+/// Return the events and the length of a link reference definition at the
+/// start of `text`, like `[label]: <destination> "title"`.
+///
+/// The destination may be on the line after the label, and the title on the
+/// lines after the destination.
+pub(super) fn definition(text: &str) -> Option<(Vec<Md<'static>>, usize)> {
+    let indent = text.bytes().take(3).take_while(|&b| b == b' ').count();
+    let rest = text[indent..].strip_prefix('[')?;
+    let len = closing_bracket(rest)?;
+    let label = rest[..len].trim();
+    let after = indent + 1 + len + 1;
+
+    if label.is_empty() || label.starts_with('^') {
+        return None;
+    }
+
+    let start = skip_space(
+        text,
+        text[after..].strip_prefix(':').map(|_| after + 1)?,
+        true,
+    );
+    let (destination, len) = destination(&text[start..])?;
+    let destination = start + destination.start..start + destination.end;
+    let after = start + len;
+    let line_end = |at: usize| {
+        let end = skip_space(text, at, false);
+
+        match text[end..].strip_prefix('\n') {
+            Some(_) => Some(end + 1),
+            None => (end == text.len()).then_some(end),
+        }
+    };
+
+    // Comment `[text]: #`
+    if &text[start..after] == "#" {
+        if let Some(end) = line_end(after) {
+            return Some((vec![Md::Comment(label.to_string().into())], end));
+        }
+    }
+
+    let mut mds = vec![
+        Md::LinkKey(label.to_string().into()),
+        Md::LinkVal(text[destination].to_string().into()),
+    ];
+
+    // Title on the same line, or on the following lines
+    let start = skip_space(text, after, true);
+
+    if start > after {
+        if let Some((title, len)) = title(&text[start..]) {
+            if let Some(end) = line_end(start + len) {
+                let title = &text[start + title.start..start + title.end];
+
+                mds.push(Md::Title(title.to_string().into()));
+                return Some((mds, end));
+            }
+        }
+    }
+
+    Some((mds, line_end(after)?))
+}
+
+/// Return the byte ranges of the destination and any title, and the length
+/// up to and including the `)` of an inline link after its `(`.
+fn inline_link(
+    text: &str,
+) -> Option<(Range<usize>, Option<Range<usize>>, usize)> {
+    let start = skip_space(text, 0, true);
+    let (destination, len) = destination(&text[start..]).unwrap_or((0..0, 0));
+    let destination = start + destination.start..start + destination.end;
+    let after = start + len;
+    let start = skip_space(text, after, true);
+    let (title, after) = match title(&text[start..]) {
+        Some((title, len)) if start > after => {
+            (Some(start + title.start..start + title.end), start + len)
+        }
+        _ => (None, after),
+    };
+    let end = skip_space(text, after, true);
+
+    text[end..]
+        .starts_with(')')
+        .then_some((destination, title, end + 1))
+}
+
+/// Return the byte range and the length of a link destination at the start
+/// of `text`, either within `<` and `>` or without spaces.
+fn destination(text: &str) -> Option<(Range<usize>, usize)> {
+    if let Some(rest) = text.strip_prefix('<') {
+        let len = rest.find(['>', '<', '\n'])?;
+
+        return rest[len..]
+            .starts_with('>')
+            .then_some((1..len + 1, len + 2));
+    }
+
+    let mut depth = 0;
+    let mut escaped = false;
+    let mut len = text.len();
+
+    for (i, b) in text.bytes().enumerate() {
+        match b {
+            _ if escaped => {}
+            b'(' => depth += 1,
+            b')' if depth == 0 => {
+                len = i;
+                break;
+            }
+            b')' => depth -= 1,
+            _ if b.is_ascii_whitespace() || b.is_ascii_control() => {
+                len = i;
+                break;
+            }
+            _ => {}
+        }
+        escaped = b == b'\\' && !escaped;
+    }
+
+    (len > 0 && depth == 0).then_some((0..len, len))
+}
+
+/// Return the byte range and the length of a link title at the start of
+/// `text`, within `"`, `'` or `(` and `)`, which may span lines but not a
+/// blank line.
+fn title(text: &str) -> Option<(Range<usize>, usize)> {
+    let close = match text.bytes().next()? {
+        b'"' => b'"',
+        b'\'' => b'\'',
+        b'(' => b')',
+        _ => return None,
+    };
+    let mut escaped = false;
+
+    for (i, b) in text.bytes().enumerate().skip(1) {
+        if b == close && !escaped {
+            let title = &text[1..i];
+
+            if title.split('\n').skip(1).any(|line| line.trim().is_empty()) {
+                return None;
+            }
+
+            return Some((1..i, i + 1));
+        }
+        escaped = b == b'\\' && !escaped;
+    }
+
+    None
+}
+
+/// Return the length of the text up to the `]` that closes a `[` before it,
+/// skipping nested and escaped brackets and code spans.
+fn closing_bracket(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut escaped = false;
+    let mut i = 0;
+
+    while let Some(&b) = text.as_bytes().get(i) {
+        match b {
+            _ if escaped => {}
+            // Code spans take precedence over brackets
+            b'`' => {
+                i += code_span(&text[i..])
+                    .map_or_else(|| backticks(&text[i..]), |(_, len)| len);
+                continue;
+            }
+            b'[' => depth += 1,
+            b']' if depth == 0 => return Some(i),
+            b']' => depth -= 1,
+            _ => {}
+        }
+        escaped = b == b'\\' && !escaped;
+        i += 1;
+    }
+
+    None
+}
+
+/// Return the byte offset after spaces and tabs (and one line ending, if
+/// `newline`) starting at byte offset `start`.
+fn skip_space(text: &str, start: usize, newline: bool) -> usize {
+    let mut end = start;
+    let mut newline = newline;
+
+    for b in text[start..].bytes() {
+        match b {
+            b' ' | b'\t' => {}
+            b'\n' if newline => newline = false,
+            _ => break,
+        }
+        end += 1;
+    }
+
+    end
+}
+
+/// Return the length of the label if `text` starts with a footnote label
+/// ending with `]`.
+pub(super) fn footnote_label(text: &str) -> Option<usize> {
+    let len = text.find(']')?;
+    let label = &text[..len];
+
+    if label.is_empty()
+        || label.contains(|c: char| c.is_whitespace() || c == '[')
+    {
+        return None;
+    }
+
+    Some(len)
+}
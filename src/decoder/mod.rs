@@ -0,0 +1,1588 @@
+mod autolink;
+mod emphasis;
+mod inline;
+
+use std::{
+    borrow::Cow,
+    collections::{HashSet, VecDeque},
+    io::Read,
+    mem,
+    ops::Range,
+    slice::SliceIndex,
+    str,
+};
+
+use crate::{
+    decode::{Error, Result, Warning, WarningKind},
+    line_reader::LineReader,
+    Md,
+};
+
+const HEADING1: &str = "#";
+const HEADING2: &str = "##";
+const HEADING3: &str = "###";
+const HEADING4: &str = "####";
+const HEADING5: &str = "#####";
+const HEADING6: &str = "######";
+// This one is invalid, warns and outputs a paragraph
+const HEADING7: &str = "#######";
+
+/// Block that contains other blocks
+enum Container {
+    /// Block quote, continued by lines starting with `>`
+    Quote,
+    /// List, continued by items with the same bullet
+    List {
+        /// Bullet character, delimiter after the number if ordered, or `:`
+        /// for definition lists
+        bullet: u8,
+        /// Index of the list's first queued event
+        start: usize,
+        /// Whether items are separated by blank lines
+        loose: bool,
+    },
+    /// Admonition or details, continued by lines indented 4 spaces
+    Indented,
+    /// Footnote definition, continued by lines indented 4 spaces
+    Footnote,
+    /// List item, continued by lines indented to its content
+    Item {
+        /// Column of the item's content
+        indent: usize,
+        /// Whether a blank line was seen since the item's last content
+        blank: bool,
+    },
+}
+
+/// List item marker
+struct ListMarker {
+    /// Bullet character, or delimiter after the number if ordered
+    bullet: u8,
+    /// Number of ordered list item
+    number: Option<u32>,
+    /// Column of the item's content
+    indent: usize,
+    /// Length in bytes of marker and following whitespace
+    len: usize,
+    /// Whether the item is empty on its first line
+    empty: bool,
+}
+
+impl ListMarker {
+    /// Return true if the item can start a list that interrupts a paragraph
+    /// (not empty, and if ordered starting at 1).
+    fn interrupts(&self) -> bool {
+        !self.empty && self.number.map_or(true, |number| number == 1)
+    }
+}
+
+/// Open code block or HTML comment
+struct Fence<'a> {
+    /// Fence character, `` ` `` or `~`, a space if indented, or `<` for an
+    /// HTML comment
+    marker: u8,
+    /// Number of fence characters
+    len: usize,
+    /// Indentation of the opening fence, removed from content lines
+    indent: usize,
+    /// Info string after the opening fence
+    info: Cow<'a, str>,
+    /// Content lines, each ending in a newline
+    body: String,
+}
+
+/// Markdown decoder
+pub struct Decoder<'a> {
+    line_reader: LineReader<'a>,
+    containers: Vec<Container>,
+    /// Number of the line being decoded
+    line_number: u16,
+    paragraph: Vec<Cow<'a, str>>,
+    /// Number of the open paragraph's first line
+    paragraph_line: u16,
+    fence: Option<Fence<'a>>,
+    /// Number of columns of the open table
+    table: Option<usize>,
+    /// Whether the open paragraph is an admonition title (or its body after
+    /// the line of an alert's kind)
+    title: Option<bool>,
+    queued: VecDeque<Md<'a>>,
+    /// Index of queued paragraph that may become definition terms, with the
+    /// number of its first line and its lines
+    terms: Option<(usize, u16, Vec<Cow<'a, str>>)>,
+    /// Labels of footnote definitions, lowercase
+    footnotes: HashSet<String>,
+    /// Footnote references with their label, line number and line text
+    footnote_refs: Vec<(String, u16, Cow<'a, str>)>,
+    warnings: VecDeque<Warning<'a>>,
+    /// Index of queued close, start and looseness of definition list that
+    /// may continue with more terms
+    reopen: Option<(usize, usize, bool)>,
+    /// Lines of the front matter opening the document, until closed
+    front_matter: Option<Vec<Cow<'a, str>>>,
+    html_comments: bool,
+    strict: bool,
+    done: bool,
+}
+
+impl<'a> Decoder<'a> {
+    /// Create markdown decoder from I/O reader.
+    pub fn from_reader(md: impl Read + 'a) -> Self {
+        Self::from(LineReader::from_reader(md))
+    }
+
+    /// Create markdown decoder from string slice.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(md: &'a str) -> Self {
+        Self::from_slice(md.as_bytes())
+    }
+
+    /// Create markdown decoder from byte slice.
+    pub fn from_slice(md: &'a [u8]) -> Self {
+        Self::from(LineReader::from_slice(md))
+    }
+
+    /// Decode HTML comments `<!-- -->` starting a line as comments, instead
+    /// of as text.
+    pub fn html_comments(mut self, html_comments: bool) -> Self {
+        self.html_comments = html_comments;
+        self
+    }
+
+    /// Decode strictly following CommonMark where markdown flavors differ,
+    /// with `#Text` (no space after `#`) being a paragraph instead of a
+    /// heading (the default).
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    fn decode_line(&mut self, line: Cow<'a, str>) {
+        let blank = line.trim().is_empty();
+
+        if let Some(lines) = &mut self.front_matter {
+            let closing = line.trim_end() == lines[0].trim_end()
+                || (line.trim_end() == "..." && lines[0].starts_with('-'));
+
+            // A blank line right after the delimiter makes it a thematic
+            // break (or setext underline) instead
+            let opening = lines.len() == 1;
+
+            lines.push(line);
+            if opening && blank {
+                self.reopen_front_matter();
+            } else if closing {
+                self.close_front_matter();
+            }
+            return;
+        }
+
+        self.decode_blocks(expand_tabs(line));
+
+        // Paragraphs may become definition terms until more content is seen
+        if !blank {
+            self.terms = None;
+            if self.reopen.is_some_and(|(close, ..)| {
+                close + 1 != self.queued.len() || self.paragraph.is_empty()
+            }) {
+                self.drop_reopen();
+            }
+        }
+    }
+
+    fn decode_blocks(&mut self, line: Cow<'a, str>) {
+        let mut start = 0;
+        let mut matched = 0;
+
+        for container in &self.containers {
+            let rest = &line[start..];
+            let len = match *container {
+                Container::Quote => quote_marker(rest),
+                Container::List { .. } => Some(0),
+                Container::Indented | Container::Footnote => {
+                    item_continues(rest, 4)
+                }
+                Container::Item { indent, .. } => item_continues(rest, indent),
+            };
+            let Some(len) = len else {
+                break;
+            };
+
+            start += len;
+            matched += 1;
+        }
+
+        // Lists only continue without their item for a new sibling item
+        if matched > 0 && matched < self.containers.len() {
+            if let Container::List { bullet, .. } = self.containers[matched - 1]
+            {
+                let rest = &line[start..];
+                let sibling = if bullet == b':' {
+                    definition_marker(rest).is_some()
+                } else {
+                    !thematic_break(rest)
+                        && list_marker(rest).map(|m| m.bullet) == Some(bullet)
+                };
+
+                if !sibling {
+                    matched -= 1;
+                }
+            }
+        }
+
+        let mut opened = false;
+
+        loop {
+            let rest = &line[start..];
+
+            // Fenced code content isn't parsed for blocks
+            if !opened
+                && self.fence.as_ref().is_some_and(|f| f.marker != b' ')
+                && matched == self.containers.len()
+            {
+                break;
+            }
+
+            if let Some(len) = quote_marker(rest) {
+                if !opened {
+                    self.close_containers(matched);
+                    opened = true;
+                }
+
+                self.start_block();
+                self.containers.push(Container::Quote);
+                self.queued.push_back(Md::QuoteOpen);
+                start += len;
+                continue;
+            }
+
+            // Admonitions can't interrupt a paragraph
+            if let Some((kind, title)) = admonition_marker(rest, "!!!")
+                .filter(|_| self.block_boundary(matched, opened))
+            {
+                self.open_indented(matched, opened);
+                self.queued.push_back(Md::Admonition(slice(
+                    &line,
+                    start + kind.start..start + kind.end,
+                )));
+                if let Some(title) = title {
+                    let title =
+                        slice(&line, start + title.start..start + title.end);
+
+                    self.queue_inline(vec![title], self.line_number);
+                }
+                self.clear_blank();
+                return;
+            }
+
+            // Details can't interrupt a paragraph
+            if let Some((expanded, kind, summary)) = details_marker(rest)
+                .filter(|_| self.block_boundary(matched, opened))
+            {
+                self.open_indented(matched, opened);
+                // Kind of `???` and `???+`, which `++` and `+++` don't have
+                if let Some(kind) = &kind {
+                    self.queued.push_back(Md::Admonition(slice(
+                        &line,
+                        start + kind.start..start + kind.end,
+                    )));
+                }
+                self.queued.push_back(Md::Details(
+                    slice(&line, start + summary.start..start + summary.end),
+                    expanded,
+                ));
+                // Summary of `++` and `+++` continues on the next lines
+                if kind.is_none() {
+                    self.title = Some(true);
+                }
+                self.clear_blank();
+                return;
+            }
+
+            // Footnote definitions can't interrupt a paragraph
+            if let Some((label, len)) = footnote_marker(rest) {
+                if self.block_boundary(matched, opened) {
+                    if !opened {
+                        self.close_containers(matched);
+                        opened = true;
+                    }
+
+                    let label =
+                        slice(&line, start + label.start..start + label.end);
+
+                    self.start_block();
+                    self.footnotes.insert(label.to_lowercase());
+                    self.containers.push(Container::Footnote);
+                    self.queued.push_back(Md::FootnoteOpen(label));
+                    start += len;
+                    continue;
+                }
+            }
+
+            if let Some(marker) = definition_marker(rest) {
+                if !opened && self.open_definition(matched) {
+                    self.containers.push(Container::Item {
+                        indent: marker.indent,
+                        blank: false,
+                    });
+                    self.queued.push_back(Md::ListItem);
+                    start += marker.len;
+                    opened = true;
+                    continue;
+                }
+            }
+
+            let Some(marker) = list_marker(rest) else {
+                break;
+            };
+
+            if thematic_break(rest)
+                || (!marker.interrupts()
+                    && !opened
+                    && matched == self.containers.len()
+                    && !self.paragraph.is_empty())
+            {
+                break;
+            }
+
+            if !opened {
+                self.close_containers(matched);
+                opened = true;
+            }
+
+            match self.containers.last() {
+                Some(Container::List { bullet, .. })
+                    if *bullet == marker.bullet => {}
+                _ => {
+                    self.start_block();
+                    self.containers.push(Container::List {
+                        bullet: marker.bullet,
+                        start: self.queued.len(),
+                        loose: false,
+                    });
+                    self.queued.push_back(match marker.number {
+                        None => Md::UnorderedList,
+                        Some(1) if marker.bullet == b'.' => Md::OrderedList,
+                        Some(number) => {
+                            Md::OrderedListStart(number, marker.bullet.into())
+                        }
+                    });
+                }
+            }
+
+            self.containers.push(Container::Item {
+                indent: marker.indent,
+                blank: false,
+            });
+            self.queued.push_back(Md::ListItem);
+            start += marker.len;
+
+            if let Some((checked, len)) = task_marker(&line[start..]) {
+                self.queued.push_back(Md::ListTask(checked));
+                start += len;
+                break;
+            }
+        }
+
+        if !opened && matched < self.containers.len() {
+            let rest = &line[start..];
+
+            // Lazy continuation of a paragraph in an unmatched container
+            if (!self.paragraph.is_empty() || self.title.is_some())
+                && !rest.trim().is_empty()
+                && !interrupts_paragraph(rest, self.strict)
+            {
+                let ws = rest.len() - rest.trim_start().len();
+
+                self.push_paragraph(slice(&line, start + ws..));
+                self.clear_blank();
+                return;
+            }
+
+            self.close_containers(matched);
+        }
+
+        // The empty line of an item's own marker isn't a blank line in it
+        if opened
+            && matches!(self.containers.last(), Some(Container::Item { .. }))
+            && line[start..].trim().is_empty()
+        {
+            return;
+        }
+
+        self.decode_leaf(line, start);
+    }
+
+    fn decode_leaf(&mut self, line: Cow<'a, str>, start: usize) {
+        let rest = &line[start..];
+
+        if let Some(fence @ Fence { marker: b' ', .. }) = &mut self.fence {
+            let blank = rest.trim().is_empty();
+
+            if blank || indentation(rest, 4).len() == 4 {
+                let indent = indentation(rest, 4).len();
+
+                fence.body.push_str(&rest[indent..]);
+                fence.body.push('\n');
+                // Blank lines may end the code and the list item around it
+                for container in &mut self.containers {
+                    if let Container::Item { blank: b, .. } = container {
+                        *b = blank;
+                    }
+                }
+                return;
+            }
+
+            self.close_fence();
+        }
+
+        if let Some(Fence { marker: b'<', .. }) = self.fence {
+            self.comment(line, start);
+            return;
+        }
+
+        if let Some(fence) = &mut self.fence {
+            if closing_fence(rest, fence.marker, fence.len) {
+                self.close_fence();
+                self.clear_blank();
+                return;
+            }
+
+            let indent = indentation(rest, fence.indent).len();
+
+            fence.body.push_str(&rest[indent..]);
+            fence.body.push('\n');
+            return;
+        }
+
+        // Table rows continue until a blank line or another block
+        if let Some(columns) = self.table {
+            if !rest.trim().is_empty()
+                && !interrupts_paragraph(rest, self.strict)
+            {
+                self.table_row(
+                    slice(&line, start..),
+                    columns,
+                    self.line_number,
+                );
+                self.clear_blank();
+                return;
+            }
+            self.table = None;
+        }
+
+        if rest.trim().is_empty() {
+            if !self.paragraph.is_empty() {
+                self.terms = Some((
+                    self.queued.len(),
+                    self.paragraph_line,
+                    self.paragraph.clone(),
+                ));
+            }
+            self.close_paragraph(Md::Paragraph);
+            for container in &mut self.containers {
+                if let Container::Item { blank, .. } = container {
+                    *blank = true;
+                }
+            }
+            return;
+        }
+
+        // Indented code can't interrupt a paragraph
+        if self.paragraph.is_empty() && indentation(rest, 4).len() == 4 {
+            self.start_block();
+            self.fence = Some(Fence {
+                marker: b' ',
+                len: 0,
+                indent: 4,
+                info: Cow::Borrowed(""),
+                body: format!("{}\n", &rest[4..]),
+            });
+            self.clear_blank();
+            return;
+        }
+
+        // A one line paragraph may be the header row of a table
+        if self.paragraph.len() == 1 {
+            if let Some(columns) = table_delimiter(rest) {
+                if table_cells(&self.paragraph[0]).len() == columns.len() {
+                    let header = mem::take(&mut self.paragraph).remove(0);
+                    let count = columns.len();
+
+                    self.table = Some(count);
+                    self.queued.extend(columns);
+                    self.table_row(header, count, self.paragraph_line);
+                    self.clear_blank();
+                    return;
+                }
+            }
+        }
+
+        if let Some(heading) = setext_underline(rest) {
+            if !self.paragraph.is_empty() {
+                self.close_paragraph(heading);
+                self.clear_blank();
+                return;
+            }
+        }
+
+        if let Some(comment) = self
+            .html_comments
+            .then(|| indentation(rest, 3).len())
+            .filter(|&indent| rest[indent..].starts_with("<!--"))
+        {
+            self.close_paragraph(Md::Paragraph);
+            self.start_block();
+            self.fence = Some(Fence {
+                marker: b'<',
+                len: 0,
+                indent: 0,
+                info: Cow::Borrowed(""),
+                body: String::new(),
+            });
+            self.clear_blank();
+            self.comment(line, start + comment + 4);
+            return;
+        }
+
+        if let Some((marker, len, indent)) = fence_marker(rest) {
+            let info = &rest[indent + len..];
+            let ws = info.len() - info.trim_start().len();
+            let end = start + rest.trim_end().len();
+            // Spaces after the marker with no info string end the line
+            let info = (start + indent + len + ws).min(end)..end;
+
+            self.close_paragraph(Md::Paragraph);
+            self.start_block();
+            self.fence = Some(Fence {
+                marker,
+                len,
+                indent,
+                info: slice(&line, info),
+                body: String::new(),
+            });
+            self.clear_blank();
+            return;
+        }
+
+        if thematic_break(rest) {
+            self.close_paragraph(Md::Paragraph);
+            self.start_block();
+            self.queued.push_back(Md::HorizontalRule);
+            self.clear_blank();
+            return;
+        }
+
+        if ambiguous_heading(rest) {
+            self.warnings.push_back(Warning::new(
+                slice(&line, start..),
+                self.line_number,
+                WarningKind::AmbiguousHeading,
+            ));
+        }
+
+        if let Some((heading, text)) = atx_heading(rest, self.strict) {
+            self.close_paragraph(Md::Paragraph);
+            self.start_block();
+            let mut text = slice(&line, start + text..);
+
+            self.queued.push_back(heading);
+            self.heading_id(&mut text);
+            self.queue_inline(vec![text], self.line_number);
+            self.clear_blank();
+            return;
+        }
+
+        let ws = rest.len() - rest.trim_start().len();
+
+        // GitHub alerts start block quotes with `[!KIND]`
+        if self.paragraph.is_empty()
+            && matches!(self.containers.last(), Some(Container::Quote))
+            && matches!(self.queued.back(), Some(Md::QuoteOpen))
+        {
+            if let Some((kind, text)) = alert_marker(rest) {
+                let title = &rest[text..];
+                let ws = title.len() - title.trim_start().len();
+
+                self.queued.push_back(Md::Admonition(slice(
+                    &line,
+                    start + kind.start..start + kind.end,
+                )));
+                // Any title ends with the line, and like GitHub, the next
+                // line starts the body
+                if !title.trim().is_empty() {
+                    let end = start + rest.trim_end().len();
+                    let title = slice(&line, start + text + ws..end);
+
+                    self.queue_inline(vec![title], self.line_number);
+                }
+                self.title = Some(false);
+                self.clear_blank();
+                return;
+            }
+        }
+
+        if self.paragraph.is_empty() {
+            self.start_block();
+        }
+        self.push_paragraph(slice(&line, start + ws..));
+        self.clear_blank();
+    }
+
+    /// Prepare a definition list for a new definition item, returning false if
+    /// there's no list or paragraph before it to define.
+    fn open_definition(&mut self, matched: usize) -> bool {
+        // Another definition for the same terms
+        if matched < self.containers.len() {
+            let sibling = matched > 0
+                && matches!(
+                    self.containers[matched - 1],
+                    Container::List { bullet: b':', .. },
+                );
+
+            if sibling {
+                self.close_containers(matched);
+            }
+
+            return sibling;
+        }
+
+        let (terms, line, loose) = if !self.paragraph.is_empty() {
+            (mem::take(&mut self.paragraph), self.paragraph_line, false)
+        } else {
+            // Paragraph before a blank line, makes the list loose
+            let Some((index, line, terms)) = self.terms.take() else {
+                return false;
+            };
+
+            if self.queued.get(index) != Some(&Md::Paragraph) {
+                return false;
+            }
+
+            self.queued.truncate(index);
+            (terms, line, true)
+        };
+
+        match self.reopen.take() {
+            // Continue the definition list right before the terms
+            Some((close, start, was_loose))
+                if close + 1 == self.queued.len() =>
+            {
+                self.queued.pop_back();
+                self.containers.push(Container::List {
+                    bullet: b':',
+                    start,
+                    loose: loose || was_loose,
+                });
+            }
+            reopen => {
+                self.reopen = reopen;
+                self.drop_reopen();
+                self.start_block();
+                self.containers.push(Container::List {
+                    bullet: b':',
+                    start: self.queued.len(),
+                    loose,
+                });
+                self.queued.push_back(Md::DefinitionList);
+            }
+        }
+
+        for (term, line) in terms.into_iter().zip(line..) {
+            self.queued.push_back(Md::DefinitionTerm);
+            self.queue_inline(vec![term], line);
+        }
+
+        true
+    }
+
+    /// Return true if a block that can't interrupt a paragraph may start,
+    /// after opening or not matching containers, or with no open paragraph.
+    fn block_boundary(&self, matched: usize, opened: bool) -> bool {
+        opened || matched < self.containers.len() || self.paragraph.is_empty()
+    }
+
+    /// Open an admonition or details block, closing unmatched containers if
+    /// none were opened on this line.
+    fn open_indented(&mut self, matched: usize, opened: bool) {
+        if !opened {
+            self.close_containers(matched);
+        }
+
+        self.start_block();
+        self.containers.push(Container::Indented);
+        self.queued.push_back(Md::QuoteOpen);
+    }
+
+    /// Stop waiting for terms to continue the last definition list.
+    fn drop_reopen(&mut self) {
+        if let Some((_, start, loose)) = self.reopen.take() {
+            if !loose {
+                self.tighten(start);
+            }
+        }
+    }
+
+    /// Make the list loose if a block starts after a blank line in its item.
+    fn start_block(&mut self) {
+        let len = self.containers.len();
+
+        if let Some(Container::Item { blank: true, .. }) =
+            self.containers.last()
+        {
+            if let Container::List { loose, .. } = &mut self.containers[len - 2]
+            {
+                *loose = true;
+            }
+        }
+    }
+
+    /// Forget blank lines seen in list items, after non-blank content.
+    fn clear_blank(&mut self) {
+        for container in &mut self.containers {
+            if let Container::Item { blank, .. } = container {
+                *blank = false;
+            }
+        }
+    }
+
+    /// Close the open paragraph or code block and all but the outermost
+    /// `keep` containers.
+    fn close_containers(&mut self, keep: usize) {
+        self.close_paragraph(Md::Paragraph);
+        self.close_fence();
+        self.table = None;
+
+        while self.containers.len() > keep {
+            let index = self.containers.len() - 1;
+
+            match self.containers.pop() {
+                Some(Container::Quote | Container::Indented) => {
+                    self.queued.push_back(Md::QuoteClose)
+                }
+                Some(Container::Footnote) => {
+                    self.queued.push_back(Md::FootnoteClose)
+                }
+                Some(Container::List {
+                    bullet: b':',
+                    start,
+                    loose,
+                }) => {
+                    // Wait for following terms to continue the list
+                    self.drop_reopen();
+                    self.queued.push_back(Md::ListClose);
+                    self.reopen = Some((self.queued.len() - 1, start, loose));
+                }
+                Some(Container::List { start, loose, .. }) => {
+                    if !loose {
+                        self.tighten(start);
+                    }
+                    self.queued.push_back(Md::ListClose);
+                }
+                Some(Container::Item { blank, .. }) => {
+                    // Blank line between sibling items
+                    if blank && index == keep {
+                        if let Some(Container::List { loose, .. }) =
+                            self.containers.last_mut()
+                        {
+                            *loose = true;
+                        }
+                    }
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Remove paragraphs directly within items of the tight list queued at
+    /// `start`.
+    fn tighten(&mut self, start: usize) {
+        let mut depth = 0;
+        let mut index = start + 1;
+
+        while index < self.queued.len() {
+            match self.queued[index] {
+                Md::QuoteOpen
+                | Md::UnorderedList
+                | Md::OrderedList
+                | Md::OrderedListStart(..)
+                | Md::DefinitionList => depth += 1,
+                Md::QuoteClose | Md::ListClose => depth -= 1,
+                Md::Paragraph if depth == 0 => {
+                    self.queued.remove(index);
+                    continue;
+                }
+                _ => {}
+            }
+            index += 1;
+        }
+    }
+
+    /// Flush buffered paragraph lines, opened with `block`.
+    fn close_paragraph(&mut self, block: Md<'a>) {
+        let title = self.title.take();
+
+        let definitions = self
+            .paragraph
+            .first()
+            .is_some_and(|line| line.trim_start().starts_with('['));
+
+        if title.is_none() && definitions {
+            self.definitions();
+        }
+
+        if self.paragraph.is_empty() {
+            return;
+        }
+
+        let heading = matches!(block, Md::Heading1 | Md::Heading2);
+        let paragraph = title.is_none() && block == Md::Paragraph;
+
+        if title != Some(true) {
+            self.queued.push_back(block);
+        }
+
+        let mut lines = mem::take(&mut self.paragraph);
+
+        if let Some(last) = lines.last_mut().filter(|_| heading) {
+            self.heading_id(last);
+        }
+
+        let start = self.queued.len();
+
+        self.queue_inline(lines, self.paragraph_line);
+        if paragraph {
+            self.caption(start);
+        }
+    }
+
+    /// Mark the content after an image starting a paragraph queued from index
+    /// `start` as its caption, replacing the line break or space before it.
+    fn caption(&mut self, start: usize) {
+        if !matches!(
+            self.queued.get(start),
+            Some(Md::ImageRef(_) | Md::ImageNum(..))
+        ) {
+            return;
+        }
+
+        let mut index = start + 1;
+
+        while matches!(
+            self.queued.get(index),
+            Some(Md::LinkLabel(_) | Md::LinkVal(_) | Md::Title(_))
+        ) {
+            index += 1;
+        }
+
+        if let Some(Md::Text(text)) = self.queued.get(index) {
+            if text.trim().is_empty() && self.queued.len() > index + 1 {
+                self.queued.remove(index);
+            }
+        }
+
+        let last = self.queued.len() == index + 1;
+
+        match self.queued.get_mut(index) {
+            Some(Md::Text(text)) if last && text.trim().is_empty() => return,
+            Some(Md::Text(Cow::Borrowed(text))) => *text = text.trim_start(),
+            Some(Md::Text(Cow::Owned(text))) => {
+                *text = text.trim_start().to_string();
+            }
+            Some(_) => {}
+            None => return,
+        }
+
+        self.queued.insert(index, Md::Caption);
+    }
+
+    /// Queue the custom ID `{#id}` ending the text of a heading, if any,
+    /// removing it from the text.
+    fn heading_id(&mut self, text: &mut Cow<'a, str>) {
+        if let Some((len, id)) = custom_id(text) {
+            self.queued.push_back(Md::HeadingId(slice(text, id)));
+            *text = slice(text, ..len);
+        }
+    }
+
+    /// Queue the link reference definitions at the start of the open
+    /// paragraph, removing their lines.
+    fn definitions(&mut self) {
+        let text = self.paragraph.join("\n");
+        let mut start = 0;
+
+        while let Some((mds, len)) = inline::definition(&text[start..]) {
+            self.queued.extend(mds);
+            start += len;
+        }
+
+        let lines = text[..start].matches('\n').count()
+            + usize::from(start == text.len());
+
+        self.paragraph.drain(..lines);
+        self.paragraph_line = self
+            .paragraph_line
+            .saturating_add(lines.try_into().unwrap_or(u16::MAX));
+    }
+
+    /// Warn about footnote references without a definition.
+    fn undefined_footnotes(&mut self) {
+        for (label, line, text) in mem::take(&mut self.footnote_refs) {
+            if !self.footnotes.contains(&label) {
+                self.warnings.push_back(Warning::new(
+                    text,
+                    line,
+                    WarningKind::UndefinedFootnote,
+                ));
+            }
+        }
+    }
+
+    /// Add a line to the open paragraph.
+    fn push_paragraph(&mut self, line: Cow<'a, str>) {
+        if self.paragraph.is_empty() {
+            self.paragraph_line = self.line_number;
+        }
+        self.paragraph.push(line);
+    }
+
+    /// Queue the inline markdown of the lines of a block starting at line
+    /// number `line`, keeping track of footnote references.
+    fn queue_inline(&mut self, lines: Vec<Cow<'a, str>>, first: u16) {
+        let texts = lines
+            .iter()
+            .any(|text| text.contains("[^"))
+            .then(|| lines.clone());
+
+        for (index, mds) in inline::decode(lines).into_iter().enumerate() {
+            let line = first.saturating_add(index.try_into().unwrap_or(0));
+
+            for md in mds {
+                if let Md::FootnoteRef(label) = &md {
+                    let text = texts
+                        .as_ref()
+                        .and_then(|texts| texts.get(index))
+                        .cloned()
+                        .unwrap_or_default();
+
+                    self.footnote_refs.push((label.to_lowercase(), line, text));
+                }
+                self.queued.push_back(md);
+            }
+        }
+    }
+
+    /// Queue a table row with `columns` cells, padded with empty cells or
+    /// with extra cells dropped.
+    fn table_row(&mut self, row: Cow<'a, str>, columns: usize, line: u16) {
+        let mut cells = table_cells(&row).into_iter();
+
+        for _ in 0..columns {
+            self.queued.push_back(Md::TableCell);
+
+            let Some(cell) = cells.next().filter(|cell| !cell.is_empty())
+            else {
+                continue;
+            };
+            let text = slice(&row, cell);
+            let text = if text.contains("\\|") {
+                Cow::Owned(text.replace("\\|", "|"))
+            } else {
+                text
+            };
+
+            self.queue_inline(vec![text], line);
+        }
+    }
+
+    /// Add the text of the line from byte offset `start` to the open HTML
+    /// comment, until `-->` closes it and the rest of the line is decoded.
+    fn comment(&mut self, line: Cow<'a, str>, start: usize) {
+        let Some(fence) = &mut self.fence else {
+            return;
+        };
+        let rest = &line[start..];
+        let Some(end) = rest.find("-->") else {
+            fence.body.push_str(rest);
+            fence.body.push('\n');
+            return;
+        };
+
+        fence.body.push_str(&rest[..end]);
+        self.close_fence();
+
+        let after = &rest[end + 3..];
+        let after = start + end + 3 + after.len() - after.trim_start().len();
+
+        if after < line.len() {
+            self.decode_leaf(line, after);
+        }
+    }
+
+    /// Queue the closed front matter.
+    fn close_front_matter(&mut self) {
+        let Some(lines) = self.front_matter.take() else {
+            return;
+        };
+        let mut raw = lines.join("\n");
+
+        raw.push('\n');
+        self.queued.push_back(Md::FrontMatter(Cow::Owned(raw)));
+    }
+
+    /// Decode the lines of front matter that isn't closed as markdown.
+    fn reopen_front_matter(&mut self) {
+        let Some(lines) = self.front_matter.take() else {
+            return;
+        };
+
+        self.line_number = 0;
+        for line in lines {
+            self.line_number = self.line_number.saturating_add(1);
+            self.decode_line(line);
+        }
+    }
+
+    /// Flush the open fenced code block or HTML comment, if any.
+    fn close_fence(&mut self) {
+        let Some(mut fence) = self.fence.take() else {
+            return;
+        };
+
+        if fence.marker == b'<' {
+            let comment = fence.body.trim().to_string();
+
+            self.queued.push_back(Md::Comment(Cow::Owned(comment)));
+            return;
+        }
+
+        // Trailing blank lines aren't part of indented code
+        if fence.marker == b' ' {
+            let end = fence.body.trim_end().len();
+            let end = fence.body[end..].find('\n').map_or(end, |i| end + i + 1);
+
+            fence.body.truncate(end);
+        }
+
+        if !fence.info.is_empty() {
+            self.queued.push_back(Md::SyntaxHighlighting(fence.info));
+        }
+        self.queued.push_back(Md::Codeblock(Cow::Owned(fence.body)));
+    }
+}
+
+impl<'a> Iterator for Decoder<'a> {
+    type Item = Result<'a, Md<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Lists are held until closed to know if they're loose
+            let holding = self.terms.is_some()
+                || self.reopen.is_some()
+                || self
+                    .containers
+                    .iter()
+                    .any(|c| matches!(c, Container::List { .. }));
+
+            if !holding {
+                if let Some(queued) = self.queued.pop_front() {
+                    return Some(Ok(queued));
+                }
+            }
+
+            if self.done {
+                return self.warnings.pop_front().map(Error::Warning).map(Err);
+            }
+
+            match self.line_reader.next() {
+                Some(Ok(line)) => {
+                    self.line_number = self.line_number.saturating_add(1);
+                    // Front matter can only open the document
+                    if self.line_number == 1
+                        && matches!(line.trim_end(), "---" | "+++")
+                    {
+                        self.front_matter = Some(vec![line]);
+                    } else {
+                        self.decode_line(line);
+                    }
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None if self.front_matter.is_some() => {
+                    self.reopen_front_matter();
+                }
+                None => {
+                    self.close_containers(0);
+                    self.terms = None;
+                    self.drop_reopen();
+                    self.undefined_footnotes();
+                    self.done = true;
+                }
+            }
+        }
+    }
+}
+
+impl<'a> From<LineReader<'a>> for Decoder<'a> {
+    fn from(line_reader: LineReader<'a>) -> Self {
+        Self {
+            line_reader,
+            containers: Vec::new(),
+            line_number: 0,
+            paragraph: Vec::new(),
+            paragraph_line: 0,
+            fence: None,
+            table: None,
+            title: None,
+            queued: VecDeque::new(),
+            terms: None,
+            footnotes: HashSet::new(),
+            footnote_refs: Vec::new(),
+            warnings: VecDeque::new(),
+            reopen: None,
+            front_matter: None,
+            html_comments: false,
+            strict: false,
+            done: false,
+        }
+    }
+}
+
+/// Get a byte range of the line, without copying if borrowed.
+fn slice<'a, R>(line: &Cow<'a, str>, range: R) -> Cow<'a, str>
+where
+    R: SliceIndex<str, Output = str>,
+{
+    match line {
+        Cow::Borrowed(line) => Cow::Borrowed(&line[range]),
+        Cow::Owned(line) => Cow::Owned(line[range].to_string()),
+    }
+}
+
+/// Return the length of the `>` marker (with up to one space after it) if
+/// `line` continues or opens a block quote.
+fn quote_marker(line: &str) -> Option<usize> {
+    let indent = indentation(line, 3).len();
+    let rest = line[indent..].strip_prefix('>')?;
+    let space = usize::from(rest.starts_with([' ', '\t']));
+
+    Some(indent + 1 + space)
+}
+
+/// Return the list item marker if `line` opens a list item.
+fn list_marker(line: &str) -> Option<ListMarker> {
+    let indent = indentation(line, 3).len();
+    let digits = line[indent..]
+        .bytes()
+        .take(10)
+        .take_while(u8::is_ascii_digit)
+        .count();
+    let bullet = *line.as_bytes().get(indent + digits)?;
+    let number = match digits {
+        0 if matches!(bullet, b'-' | b'+' | b'*') => None,
+        1..=9 if matches!(bullet, b'.' | b')') => {
+            Some(line[indent..indent + digits].parse().ok()?)
+        }
+        _ => return None,
+    };
+
+    item_marker(line, indent + digits + 1, bullet, number)
+}
+
+/// Return the definition marker if `line` opens a definition with `:`.
+fn definition_marker(line: &str) -> Option<ListMarker> {
+    let indent = indentation(line, 3).len();
+
+    if line.as_bytes().get(indent) != Some(&b':') {
+        return None;
+    }
+
+    item_marker(line, indent + 1, b':', None).filter(|marker| !marker.empty)
+}
+
+/// Return the list item marker for a `bullet` ending at byte offset `end`.
+fn item_marker(
+    line: &str,
+    end: usize,
+    bullet: u8,
+    number: Option<u32>,
+) -> Option<ListMarker> {
+    let rest = &line[end..];
+    let spaces = indentation(rest, usize::MAX).len();
+
+    if rest.trim().is_empty() {
+        return Some(ListMarker {
+            bullet,
+            number,
+            indent: end + 1,
+            len: line.len(),
+            empty: true,
+        });
+    }
+
+    // Content indented 5+ spaces is code, so only the first space counts
+    let spaces = match spaces {
+        0 => return None,
+        1..=4 => spaces,
+        _ => 1,
+    };
+
+    Some(ListMarker {
+        bullet,
+        number,
+        indent: end + spaces,
+        len: end + spaces,
+        empty: false,
+    })
+}
+
+/// Return whether checked and the length of the marker with whitespace after
+/// it if `line` starts a task list item with `[ ]` or `[x]`.
+fn task_marker(line: &str) -> Option<(bool, usize)> {
+    let checked = match line.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let rest = &line[3..];
+    let content = rest.trim_start();
+
+    if content.is_empty() || content.len() == rest.len() {
+        return None;
+    }
+
+    Some((checked, line.len() - content.len()))
+}
+
+/// Return the indentation to skip if `line` continues a list item with
+/// content at column `indent`.
+fn item_continues(line: &str, indent: usize) -> Option<usize> {
+    if line.trim().is_empty() {
+        return Some(0);
+    }
+
+    (indentation(line, indent).len() == indent).then_some(indent)
+}
+
+/// Return true if `line` starts a block that ends an open paragraph.
+fn interrupts_paragraph(line: &str, strict: bool) -> bool {
+    thematic_break(line)
+        || atx_heading(line, strict).is_some()
+        || quote_marker(line).is_some()
+        || fence_marker(line).is_some()
+        || list_marker(line).is_some_and(|m| m.interrupts())
+}
+
+/// Return the byte range of the label and the length of the marker with
+/// whitespace after it if `line` starts a footnote definition, like `[^1]:`.
+fn footnote_marker(line: &str) -> Option<(Range<usize>, usize)> {
+    let indent = indentation(line, 3).len();
+    let rest = line[indent..].strip_prefix("[^")?;
+    let label = inline::footnote_label(rest)?;
+    let after = rest[label + 1..].strip_prefix(':')?;
+
+    if !after.is_empty() && !after.starts_with([' ', '\t']) {
+        return None;
+    }
+
+    let start = indent + 2;
+
+    Some((start..start + label, line.len() - after.trim_start().len()))
+}
+
+/// Return the fence character, fence length and indentation if `line` opens
+/// a fenced code block with three or more `` ` `` or `~`.
+fn fence_marker(line: &str) -> Option<(u8, usize, usize)> {
+    let indent = indentation(line, 3).len();
+    let marker = *line.as_bytes().get(indent)?;
+    let len = line[indent..].bytes().take_while(|&b| b == marker).count();
+
+    if !matches!(marker, b'`' | b'~') || len < 3 {
+        return None;
+    }
+
+    // Backtick fences can't have backticks in the info string
+    if marker == b'`' && line[indent + len..].contains('`') {
+        return None;
+    }
+
+    Some((marker, len, indent))
+}
+
+/// Return true if `line` closes a fenced code block opened with `len`
+/// `marker` characters.
+fn closing_fence(line: &str, marker: u8, len: usize) -> bool {
+    let line = &line[indentation(line, 3).len()..];
+    let fence = line.bytes().take_while(|&b| b == marker).count();
+
+    fence >= len && line[fence..].trim().is_empty()
+}
+
+/// Return the byte range of the kind and the byte offset after the marker if
+/// `line` starts with a GitHub alert marker, like `[!WARNING]`.
+fn alert_marker(line: &str) -> Option<(Range<usize>, usize)> {
+    let indent = indentation(line, 3).len();
+    let rest = line[indent..].strip_prefix("[!")?;
+    let end = rest.find(']')?;
+    let kind = &rest[..end];
+    let text = indent + 2 + end + 1;
+    let after = &line[text..];
+
+    if !["NOTE", "TIP", "IMPORTANT", "WARNING", "CAUTION"]
+        .iter()
+        .any(|k| k.eq_ignore_ascii_case(kind))
+        || !(after.is_empty() || after.starts_with([' ', '\t']))
+    {
+        return None;
+    }
+
+    Some((indent + 2..indent + 2 + end, text))
+}
+
+/// Return the byte ranges of the kind and any title if `line` opens a MkDocs
+/// block with `marker`, like `!!! warning "Title"`.
+fn admonition_marker(
+    line: &str,
+    marker: &str,
+) -> Option<(Range<usize>, Option<Range<usize>>)> {
+    let indent = indentation(line, 3).len();
+    let rest = line[indent..].strip_prefix(marker)?;
+    let start = line.len() - rest.trim_start().len();
+    let len = line[start..]
+        .bytes()
+        .take_while(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_'))
+        .count();
+    let kind = start..start + len;
+    let after = &line[kind.end..];
+
+    if len == 0 {
+        return None;
+    }
+    if after.trim().is_empty() {
+        return Some((kind, None));
+    }
+
+    // Title is quoted after a space
+    let title = after.trim();
+
+    if !after.starts_with(' ')
+        || title.len() < 2
+        || !title.starts_with('"')
+        || !title.ends_with('"')
+    {
+        return None;
+    }
+
+    let start = line.len() - after.trim_start().len() + 1;
+    let end = kind.end + after.trim_end().len() - 1;
+
+    Some((kind, (start < end).then_some(start..end)))
+}
+
+/// Return whether expanded, the byte range of any kind and the byte range of
+/// the summary (or the kind, if untitled) if `line` opens a details block,
+/// like `++ Summary` or `??? note "Summary"`.
+fn details_marker(
+    line: &str,
+) -> Option<(bool, Option<Range<usize>>, Range<usize>)> {
+    let indent = indentation(line, 3).len();
+    let rest = &line[indent..];
+
+    for (marker, expanded) in [("+++", true), ("++", false)] {
+        if let Some(summary) = rest.strip_prefix(marker) {
+            if !summary.starts_with([' ', '\t']) || summary.trim().is_empty() {
+                return None;
+            }
+
+            let start = line.len() - summary.trim_start().len();
+
+            return Some((expanded, None, start..start + summary.trim().len()));
+        }
+    }
+
+    let expanded = rest.starts_with("???+");
+    let (kind, title) =
+        admonition_marker(line, if expanded { "???+" } else { "???" })?;
+
+    Some((expanded, Some(kind.clone()), title.unwrap_or(kind)))
+}
+
+/// Return the column alignments if `line` is the delimiter row of a table,
+/// with cells of `-` optionally starting or ending with `:`.
+fn table_delimiter(line: &str) -> Option<Vec<Md<'static>>> {
+    if !line.contains('|') {
+        return None;
+    }
+
+    table_cells(line)
+        .into_iter()
+        .map(|cell| {
+            let cell = &line[cell];
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+
+            if dashes.is_empty() || dashes.bytes().any(|b| b != b'-') {
+                return None;
+            }
+
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Md::TableCentered,
+                (false, true) => Md::TableRight,
+                _ => Md::TableLeft,
+            })
+        })
+        .collect()
+}
+
+/// Get the byte ranges of the trimmed cells of a table row, separated by `|`
+/// not escaped with `\`.
+fn table_cells(line: &str) -> Vec<Range<usize>> {
+    let mut start = line.len() - line.trim_start().len();
+    let mut end = line.trim_end().len();
+
+    // Leading and trailing pipes are optional
+    if line[start..end].starts_with('|') {
+        start += 1;
+    }
+    if end > start
+        && line[start..end].ends_with('|')
+        && !line[start..end].ends_with("\\|")
+    {
+        end -= 1;
+    }
+
+    let mut cells = Vec::new();
+    let mut escaped = false;
+    let mut cell = start;
+
+    for (i, b) in line[..end].bytes().enumerate().skip(start) {
+        if b == b'|' && !escaped {
+            cells.push(cell..i);
+            cell = i + 1;
+        }
+        escaped = b == b'\\' && !escaped;
+    }
+    cells.push(cell..end);
+
+    cells
+        .into_iter()
+        .map(|cell| {
+            let text = &line[cell.clone()];
+            let start = cell.start + text.len() - text.trim_start().len();
+
+            start..start + text.trim().len()
+        })
+        .collect()
+}
+
+/// Return the heading and the byte offset of its text if `line` is an ATX
+/// (`#`-prefixed) heading, which if `strict` needs whitespace after the `#`.
+fn atx_heading(line: &str, strict: bool) -> Option<(Md<'static>, usize)> {
+    if line.starts_with(HEADING7) || (strict && ambiguous_heading(line)) {
+        return None;
+    }
+
+    for (heading_prefix, heading_md) in [
+        (HEADING6, Md::Heading6),
+        (HEADING5, Md::Heading5),
+        (HEADING4, Md::Heading4),
+        (HEADING3, Md::Heading3),
+        (HEADING2, Md::Heading2),
+        (HEADING1, Md::Heading1),
+    ] {
+        if let Some(text) = line.strip_prefix(heading_prefix) {
+            return Some((heading_md, line.len() - text.trim_start().len()));
+        }
+    }
+
+    None
+}
+
+/// Return true if `line` is a heading in some markdown flavors but not others,
+/// starting with seven or more `#`, or with `#` followed by text without
+/// whitespace between.
+fn ambiguous_heading(line: &str) -> bool {
+    let text = line.trim_start_matches('#');
+    let len = line.len() - text.len();
+
+    len >= HEADING7.len()
+        || (len > 0
+            && !text.is_empty()
+            && !text.starts_with(char::is_whitespace))
+}
+
+/// Return the length of the text before a custom ID `{#id}` ending it, and
+/// the byte range of the ID.
+fn custom_id(text: &str) -> Option<(usize, Range<usize>)> {
+    let text = text.trim_end();
+    let open = text.strip_suffix('}')?.rfind("{#")?;
+    let id = open + 2..text.len() - 1;
+
+    if id.is_empty() || text[id.clone()].contains(char::is_whitespace) {
+        return None;
+    }
+
+    Some((text[..open].trim_end().len(), id))
+}
+
+/// Return the heading if `line` underlines the paragraph before it with `=`
+/// (level 1) or `-` (level 2).
+fn setext_underline(line: &str) -> Option<Md<'static>> {
+    let underline = line.strip_prefix(indentation(line, 3))?.trim_end();
+
+    if !underline.is_empty() && underline.bytes().all(|b| b == b'=') {
+        Some(Md::Heading1)
+    } else if !underline.is_empty() && underline.bytes().all(|b| b == b'-') {
+        Some(Md::Heading2)
+    } else {
+        None
+    }
+}
+
+/// Return true if `line` is a run of three or more `-`, `_` or `*`,
+/// optionally separated by spaces or tabs.
+fn thematic_break(line: &str) -> bool {
+    let line = &line[indentation(line, 3).len()..];
+    let Some(marker) = line.bytes().next() else {
+        return false;
+    };
+
+    matches!(marker, b'-' | b'_' | b'*')
+        && line.bytes().filter(|&b| b == marker).count() >= 3
+        && line
+            .bytes()
+            .all(|b| matches!(b, b' ' | b'\t') || b == marker)
+}
+
+/// Expand tabs in the indentation of `line` to spaces, with tab stops every
+/// 4 columns.
+fn expand_tabs(line: Cow<'_, str>) -> Cow<'_, str> {
+    let lead = line
+        .bytes()
+        .take_while(|b| matches!(b, b' ' | b'\t' | b'>'))
+        .count();
+
+    if !line[..lead].contains('\t') {
+        return line;
+    }
+
+    let mut expanded = String::with_capacity(line.len() + 3);
+
+    for c in line[..lead].chars() {
+        if c == '\t' {
+            let spaces = 4 - expanded.len() % 4;
+
+            expanded.extend(std::iter::repeat(' ').take(spaces));
+        } else {
+            expanded.push(c);
+        }
+    }
+    expanded.push_str(&line[lead..]);
+
+    Cow::Owned(expanded)
+}
+
+/// Get up to `max` leading spaces of `line`.
+fn indentation(line: &str, max: usize) -> &str {
+    let spaces = line.bytes().take(max).take_while(|&b| b == b' ').count();
+
+    &line[..spaces]
+}
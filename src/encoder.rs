@@ -1,10 +1,25 @@
-use std::io::Write;
+use std::{borrow::Cow, io::Write, iter::Peekable, mem};
 
 use crate::{encode::Result, Md};
 
+/// Container block being written
+enum Container {
+    /// Block quote, admonition or footnote, with the prefix length before its
+    /// `> ` or indentation
+    Quote(usize),
+    /// List, with items written as `marker` (after `number` if ordered)
+    List {
+        marker: &'static str,
+        number: Option<u32>,
+        loose: bool,
+    },
+    /// List item, with the prefix length before its indentation
+    Item(usize),
+}
+
 /// Markdown encoder
 pub struct Encoder<'a, W: Write> {
-    iter: Box<dyn Iterator<Item = Md<'a>> + 'a>,
+    iter: Peekable<Box<dyn Iterator<Item = Md<'a>> + 'a>>,
     writer: W,
     open_paragraph: bool,
     open_h1: bool,
@@ -14,14 +29,36 @@ pub struct Encoder<'a, W: Write> {
     open_h5: bool,
     open_h6: bool,
     not_first: bool,
+    fresh: bool,
+    inline: bool,
     last_text: bool,
+    last_list: Option<&'static str>,
+    prefix: String,
+    containers: Vec<Container>,
+    setext: bool,
+    heading_width: usize,
+    /// Custom ID of the open heading
+    heading_id: Option<Cow<'a, str>>,
+    /// Whether the quoted title of an admonition is open
+    open_title: bool,
+    syntax: Option<Cow<'a, str>>,
+    indent_code: bool,
+    mkdocs: bool,
+    space_breaks: bool,
+    /// Delimiter row cells of each column of the open table
+    table: Vec<&'static str>,
+    /// Number of table cells written
+    cell: usize,
+    /// Whether the last block written is a link reference definition
+    definition: bool,
 }
 
 impl<'a, W: Write> Encoder<'a, W> {
     /// Create markdown encoder.
     pub fn new(iter: impl IntoIterator<Item = Md<'a>> + 'a, writer: W) -> Self {
         Self {
-            iter: Box::new(iter.into_iter()),
+            iter: (Box::new(iter.into_iter()) as Box<dyn Iterator<Item = _>>)
+                .peekable(),
             writer,
             open_paragraph: false,
             open_h1: false,
@@ -31,75 +68,867 @@ impl<'a, W: Write> Encoder<'a, W> {
             open_h5: false,
             open_h6: false,
             not_first: false,
+            fresh: false,
+            inline: false,
             last_text: false,
+            last_list: None,
+            prefix: String::new(),
+            containers: Vec::new(),
+            setext: false,
+            heading_width: 0,
+            heading_id: None,
+            open_title: false,
+            syntax: None,
+            indent_code: false,
+            mkdocs: false,
+            space_breaks: false,
+            table: Vec::new(),
+            cell: 0,
+            definition: false,
         }
     }
 
+    /// Write level 1 and 2 headings underlined with `=` and `-` (setext)
+    /// instead of prefixed with `#` and `##` (ATX, the default).
+    pub fn setext(mut self, setext: bool) -> Self {
+        self.setext = setext;
+        self
+    }
+
+    /// Write code blocks indented by 4 spaces instead of fenced with
+    /// backticks (the default), where the code can be read back unchanged.
+    pub fn indent_code(mut self, indent_code: bool) -> Self {
+        self.indent_code = indent_code;
+        self
+    }
+
+    /// Write admonitions with MkDocs `!!!` syntax and indented content,
+    /// instead of as GitHub alerts in block quotes (the default, for kinds
+    /// GitHub supports).
+    pub fn mkdocs(mut self, mkdocs: bool) -> Self {
+        self.mkdocs = mkdocs;
+        self
+    }
+
+    /// Write hard line breaks as two trailing spaces instead of a trailing
+    /// `\` (the default).
+    pub fn space_breaks(mut self, space_breaks: bool) -> Self {
+        self.space_breaks = space_breaks;
+        self
+    }
+
     /// Encode from the iterator some markdown.
     pub fn encode_md(&mut self) -> Result {
-        for md in &mut self.iter {
-            let not_first = self.not_first;
+        while let Some(md) = self.iter.next() {
             let last_text = self.last_text;
+            let last_list = self.last_list.take();
+            let last_definition = mem::take(&mut self.definition);
 
             self.last_text = false;
-            self.not_first = true;
-
-            let mut open = |text: &str| -> Result {
-                if not_first {
-                    self.writer.write_all(b"\n\n")?;
-                }
-                self.open_paragraph = false;
-                self.open_h1 = false;
-                self.open_h2 = false;
-                self.open_h3 = false;
-                self.open_h4 = false;
-                self.open_h5 = false;
-                self.open_h6 = false;
-                Ok(self.writer.write_all(text.as_bytes())?)
-            };
 
             match md {
                 Md::Paragraph => {
-                    open("")?;
+                    self.loosen();
+                    self.open("")?;
                     self.open_paragraph = true;
                 }
                 Md::Heading1 => {
-                    open("# ")?;
+                    self.open(if self.setext { "" } else { "# " })?;
                     self.open_h1 = true;
                 }
                 Md::Heading2 => {
-                    open("## ")?;
+                    self.open(if self.setext { "" } else { "## " })?;
                     self.open_h2 = true;
                 }
                 Md::Heading3 => {
-                    open("### ")?;
+                    self.open("### ")?;
                     self.open_h3 = true;
                 }
                 Md::Heading4 => {
-                    open("#### ")?;
+                    self.open("#### ")?;
                     self.open_h4 = true;
                 }
                 Md::Heading5 => {
-                    open("##### ")?;
+                    self.open("##### ")?;
                     self.open_h5 = true;
                 }
                 Md::Heading6 => {
-                    open("###### ")?;
+                    self.open("###### ")?;
                     self.open_h6 = true;
                 }
+                Md::FrontMatter(raw) => {
+                    self.open("")?;
+                    self.writer
+                        .write_all(raw.trim_end_matches('\n').as_bytes())?;
+                }
+                Md::HeadingId(id) => self.heading_id = Some(id),
+                // Opening the document, `---` would start front matter
+                Md::HorizontalRule if !self.not_first => self.open("***")?,
+                Md::HorizontalRule => self.open("---")?,
+                Md::QuoteOpen => {
+                    let kind = match self
+                        .iter
+                        .next_if(|md| matches!(md, Md::Admonition(_)))
+                    {
+                        Some(Md::Admonition(kind)) => Some(kind),
+                        _ => None,
+                    };
+
+                    if let Some(Md::Details(summary, expanded)) =
+                        self.iter.next_if(|md| matches!(md, Md::Details(..)))
+                    {
+                        self.details(kind, summary, expanded)?;
+                        continue;
+                    }
+
+                    let github = kind.as_deref().is_some_and(|kind| {
+                        ["NOTE", "TIP", "IMPORTANT", "WARNING", "CAUTION"]
+                            .iter()
+                            .any(|k| k.eq_ignore_ascii_case(kind))
+                    });
+
+                    match kind {
+                        Some(kind) if self.mkdocs || !github => {
+                            self.admonition(&kind)?
+                        }
+                        Some(kind) => {
+                            self.open_quote()?;
+                            self.alert(&kind)?;
+                        }
+                        None => self.open_quote()?,
+                    }
+                }
+                Md::QuoteClose => {
+                    self.close()?;
+                    // Keep empty quotes
+                    if self.fresh {
+                        self.writer
+                            .write_all(self.prefix.trim_end().as_bytes())?;
+                    }
+                    self.fresh = false;
+                    self.inline = false;
+                    if let Some(Container::Quote(len)) = self.containers.pop() {
+                        self.prefix.truncate(len);
+                    }
+                }
+                Md::Admonition(kind) => self.alert(&kind)?,
+                Md::Details(summary, expanded) => {
+                    // Without a block quote there are no blocks after it
+                    self.details(None, summary, expanded)?;
+                    if let Some(Container::Quote(len)) = self.containers.pop() {
+                        self.prefix.truncate(len);
+                    }
+                }
+                Md::UnorderedList => {
+                    let parent =
+                        self.containers.iter().rev().find_map(|c| match c {
+                            Container::List { marker, .. } => Some(*marker),
+                            _ => None,
+                        });
+                    // Switch bullets so adjacent lists stay separate, and so
+                    // lists nested on the line of their item aren't a
+                    // thematic break
+                    let marker = match (last_list, parent) {
+                        (Some("- "), _) => "* ",
+                        (_, Some("- ")) if self.inline => "* ",
+                        _ => "- ",
+                    };
+
+                    self.open_list(marker, None)?;
+                }
+                Md::OrderedList => self.open_list(". ", Some(1))?,
+                Md::OrderedListStart(number, delimiter) => {
+                    let marker = match delimiter {
+                        ')' => ") ",
+                        _ => ". ",
+                    };
+
+                    self.open_list(marker, Some(number))?;
+                }
+                Md::DefinitionList => self.open_list(":   ", None)?,
+                Md::DefinitionTerm => {
+                    // Separate groups of terms and definitions
+                    if self.close_item()? {
+                        self.writer.write_all(b"\n")?;
+                        self.writer
+                            .write_all(self.prefix.trim_end().as_bytes())?;
+                        self.writer.write_all(b"\n")?;
+                        self.fresh = true;
+                    } else if !self.fresh {
+                        self.writer.write_all(b"\n")?;
+                        self.fresh = true;
+                    }
+                }
+                Md::ListItem => {
+                    self.close_item()?;
+
+                    let next_paragraph =
+                        matches!(self.iter.peek(), Some(Md::Paragraph));
+                    let Some(Container::List {
+                        marker,
+                        number,
+                        loose,
+                    }) = self.containers.last_mut()
+                    else {
+                        continue;
+                    };
+
+                    *loose |= next_paragraph;
+                    let marker = match number {
+                        Some(number) => {
+                            *number += 1;
+                            format!("{}{marker}", *number - 1)
+                        }
+                        None => marker.to_string(),
+                    };
+
+                    if !self.inline {
+                        self.separate()?;
+                    }
+                    self.write_prefix()?;
+                    self.writer.write_all(marker.as_bytes())?;
+                    self.containers.push(Container::Item(self.prefix.len()));
+                    self.prefix.extend(marker.chars().map(|_| ' '));
+                    self.inline = true;
+                }
+                Md::ListTask(checked) => {
+                    self.write_prefix()?;
+                    self.writer.write_all(if checked {
+                        b"[x] "
+                    } else {
+                        b"[ ] "
+                    })?;
+                    self.inline = true;
+                }
+                Md::ListClose => {
+                    self.close_item()?;
+                    if let Some(Container::List { marker, .. }) =
+                        self.containers.pop()
+                    {
+                        self.last_list = Some(marker);
+                    }
+                }
+                Md::TableLeft | Md::TableCentered | Md::TableRight => {
+                    // Columns after cells start another table
+                    if self.table.is_empty() || self.cell > 0 {
+                        self.open("")?;
+                    }
+                    self.table.push(match md {
+                        Md::TableCentered => ":---:",
+                        Md::TableRight => "---:",
+                        _ => "---",
+                    });
+                }
+                Md::TableCell => {
+                    let columns = self.table.len().max(1);
+
+                    if self.cell % columns != 0 {
+                        self.writer.write_all(b" |")?;
+                    } else {
+                        if self.cell > 0 {
+                            self.end_row()?;
+                            self.writer.write_all(b"\n")?;
+                            self.writer.write_all(self.prefix.as_bytes())?;
+                        }
+                        self.writer.write_all(b"|")?;
+                    }
+                    self.cell += 1;
+                    // Separate the cell's text from the pipe
+                    if matches!(self.iter.peek(), Some(Md::Text(_))) {
+                        self.last_text = true;
+                    } else if self.iter.peek().is_some_and(is_inline) {
+                        self.writer.write_all(b" ")?;
+                    }
+                }
+                Md::FootnoteRef(label) => {
+                    self.write_prefix()?;
+                    write!(self.writer, "[^{label}]")?;
+                }
+                Md::LinkRef(text) => self.link("", &text)?,
+                Md::ImageRef(alt) => self.link("!", &alt)?,
+                Md::LineBreak => {
+                    let block_end = !self.inline_follows();
+                    let single_line = block_end
+                        || self.open_h1
+                        || self.open_h2
+                        || self.open_h3
+                        || self.open_h4
+                        || self.open_h5
+                        || self.open_h6
+                        || !self.table.is_empty();
+
+                    self.write_prefix()?;
+                    // Headings and table rows can't continue on the next line,
+                    // and a break ending a block would be read as text
+                    if single_line {
+                        self.writer.write_all(b"<br>")?;
+                    } else if self.space_breaks {
+                        self.writer.write_all(b"  \n")?;
+                        self.fresh = true;
+                    } else {
+                        self.writer.write_all(b"\\\n")?;
+                        self.fresh = true;
+                    }
+                }
+                Md::Italic(_)
+                | Md::Bold(_)
+                | Md::BoldItalic(_)
+                | Md::Superscript(_)
+                | Md::Subscript(_)
+                | Md::Strikethrough(_)
+                | Md::Highlight(_)
+                | Md::Underline(_) => {
+                    let marker = match md {
+                        Md::Italic(_) => "*",
+                        Md::Bold(_) => "**",
+                        Md::BoldItalic(_) => "***",
+                        Md::Superscript(_) => "^",
+                        Md::Subscript(_) => "~",
+                        Md::Strikethrough(_) => "~~",
+                        Md::Highlight(_) => "==",
+                        _ => "--",
+                    };
+
+                    self.write_prefix()?;
+                    self.writer.write_all(marker.as_bytes())?;
+                    self.heading_width += marker.len();
+                }
+                Md::Link(link) => {
+                    self.write_prefix()?;
+                    // `www.` links aren't autolinks within `<` and `>`
+                    if link.starts_with("www.") {
+                        self.writer.write_all(link.as_bytes())?;
+                        self.heading_width += link.chars().count();
+                    } else {
+                        write!(self.writer, "<{link}>")?;
+                        self.heading_width += link.chars().count() + 2;
+                    }
+                }
+                Md::Code(code) => {
+                    // Fence with more backticks than any run in the code
+                    let len = code
+                        .split(|c| c != '`')
+                        .map(str::len)
+                        .max()
+                        .unwrap_or_default();
+                    let fence = "`".repeat(len + 1);
+                    let pad = code.starts_with('`')
+                        || code.ends_with('`')
+                        || (code.starts_with(' ')
+                            && code.ends_with(' ')
+                            && !code.trim_matches(' ').is_empty());
+                    let pad = if pad { " " } else { "" };
+                    let code = if self.table.is_empty() {
+                        code
+                    } else {
+                        Cow::Owned(code.replace('|', "\\|"))
+                    };
+
+                    self.write_prefix()?;
+                    write!(self.writer, "{fence}{pad}{code}{pad}{fence}")?;
+                    self.heading_width +=
+                        code.chars().count() + 2 * (fence.len() + pad.len());
+                }
+                Md::Caption => {
+                    self.writer.write_all(b"\n")?;
+                    self.fresh = true;
+                }
+                Md::LinkLabel(label) => {
+                    self.write_prefix()?;
+                    write!(self.writer, "[{label}]")?;
+                    self.heading_width += label.chars().count() + 2;
+                }
+                Md::LinkNum(text, number) => {
+                    self.write_prefix()?;
+                    write!(self.writer, "[{text}][{number}]")?;
+                    self.heading_width += text.chars().count() + 4;
+                }
+                Md::ImageNum(alt, number) => {
+                    self.write_prefix()?;
+                    write!(self.writer, "![{alt}][{number}]")?;
+                    self.heading_width += alt.chars().count() + 5;
+                }
+                Md::LinkKey(label) => {
+                    if last_definition {
+                        self.writer.write_all(b"\n")?;
+                        self.fresh = true;
+                        self.write_prefix()?;
+                    } else {
+                        self.loosen();
+                        self.open("")?;
+                    }
+                    write!(self.writer, "[{label}]:")?;
+                    if let Some(Md::LinkVal(_)) = self.iter.peek() {
+                        self.writer.write_all(b" ")?;
+                        self.destination()?;
+                    }
+                    self.definition = true;
+                }
+                Md::Comment(comment) => {
+                    self.loosen();
+                    self.open("")?;
+                    self.comment(&comment)?;
+                }
+                Md::LinkVal(destination) => {
+                    self.write_prefix()?;
+                    write!(self.writer, "<{destination}>")?;
+                }
+                Md::Title(title) => {
+                    self.write_prefix()?;
+                    self.title(&title)?;
+                }
+                Md::FootnoteOpen(label) => {
+                    self.open(&format!("[^{label}]: "))?;
+                    self.containers.push(Container::Quote(self.prefix.len()));
+                    self.prefix.push_str("    ");
+                    self.inline = true;
+                }
+                Md::FootnoteClose => {
+                    self.close()?;
+                    self.inline = false;
+                    if let Some(Container::Quote(len)) = self.containers.pop() {
+                        self.prefix.truncate(len);
+                    }
+                }
+                Md::SyntaxHighlighting(info) => self.syntax = Some(info),
+                Md::Codeblock(code) => {
+                    self.code_block(&code, last_list.is_some())?
+                }
                 Md::Text(text) => {
+                    self.write_prefix()?;
                     if last_text {
                         self.writer.write_all(b" ")?;
+                        self.heading_width += 1;
                     }
 
-                    self.writer.write_all(text.as_bytes())?;
+                    if self.table.is_empty() {
+                        self.writer.write_all(text.as_bytes())?;
+                    } else {
+                        self.writer
+                            .write_all(text.replace('|', "\\|").as_bytes())?;
+                    }
+                    self.heading_width += text.chars().count();
                     self.last_text = true;
                 }
-                _ => unimplemented!(),
             }
         }
 
+        self.close()?;
         self.writer.write_all(b"\n")?;
         Ok(())
     }
+
+    /// Separate and open a new block starting with `text`.
+    fn open(&mut self, text: &str) -> Result {
+        self.separate()?;
+        self.write_prefix()?;
+        Ok(self.writer.write_all(text.as_bytes())?)
+    }
+
+    /// Close the open block, and end the line followed by a blank line (or
+    /// not, in tight lists) if not at the start of the document or a
+    /// container.
+    fn separate(&mut self) -> Result {
+        self.close()?;
+        if self.inline {
+            self.inline = false;
+            return Ok(());
+        }
+        if self.not_first && !self.fresh {
+            self.writer.write_all(b"\n")?;
+            if !self.tight() {
+                self.writer.write_all(self.prefix.trim_end().as_bytes())?;
+                self.writer.write_all(b"\n")?;
+            }
+        }
+        self.not_first = true;
+        self.fresh = true;
+        Ok(())
+    }
+
+    /// Write a link (or image, after `!`) with its text, and any label,
+    /// destination and title that follow it.
+    fn link(&mut self, marker: &str, text: &str) -> Result {
+        self.write_prefix()?;
+        write!(self.writer, "{marker}[{text}]")?;
+        self.heading_width += marker.len() + text.chars().count() + 2;
+
+        if let Some(Md::LinkLabel(label)) =
+            self.iter.next_if(|md| matches!(md, Md::LinkLabel(_)))
+        {
+            write!(self.writer, "[{label}]")?;
+            self.heading_width += label.chars().count() + 2;
+        }
+
+        if let Some(Md::LinkVal(_)) = self.iter.peek() {
+            self.writer.write_all(b"(")?;
+            self.destination()?;
+            self.writer.write_all(b")")?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a comment as `[text]: #`, or as an HTML comment if the text
+    /// can't be a link label.
+    fn comment(&mut self, comment: &str) -> Result {
+        let label = !comment.is_empty()
+            && !comment.starts_with('^')
+            && !comment.contains(['[', ']', '\n']);
+
+        if label {
+            write!(self.writer, "[{comment}]: #")?;
+            return Ok(());
+        }
+
+        self.writer.write_all(b"<!-- ")?;
+        for (i, line) in comment.replace("-->", "-- >").lines().enumerate() {
+            if i > 0 {
+                self.writer.write_all(b"\n")?;
+                self.writer.write_all(self.prefix.as_bytes())?;
+            }
+            self.writer.write_all(line.as_bytes())?;
+        }
+        self.writer.write_all(b" -->")?;
+
+        Ok(())
+    }
+
+    /// Write the link destination and any title that follow.
+    fn destination(&mut self) -> Result {
+        if let Some(Md::LinkVal(destination)) =
+            self.iter.next_if(|md| matches!(md, Md::LinkVal(_)))
+        {
+            if destination.is_empty()
+                || destination.contains(char::is_whitespace)
+            {
+                write!(self.writer, "<{destination}>")?;
+            } else {
+                self.writer.write_all(destination.as_bytes())?;
+            }
+        }
+
+        if let Some(Md::Title(title)) =
+            self.iter.next_if(|md| matches!(md, Md::Title(_)))
+        {
+            self.writer.write_all(b" ")?;
+            self.title(&title)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a link title within `"`, or `'` or `(` and `)` if it contains
+    /// `"`, continuing any following lines after the container prefix.
+    fn title(&mut self, title: &str) -> Result {
+        let (open, close) = if !title.contains('"') {
+            ('"', '"')
+        } else if !title.contains('\'') {
+            ('\'', '\'')
+        } else {
+            ('(', ')')
+        };
+
+        write!(self.writer, "{open}")?;
+        for (i, line) in title.split('\n').enumerate() {
+            if i > 0 {
+                self.writer.write_all(b"\n")?;
+                self.writer.write_all(self.prefix.as_bytes())?;
+            }
+            self.writer.write_all(line.as_bytes())?;
+        }
+        write!(self.writer, "{close}")?;
+
+        Ok(())
+    }
+
+    /// Write the container prefix if at the start of a line.
+    fn write_prefix(&mut self) -> Result {
+        if self.inline {
+            self.inline = false;
+        } else if self.fresh {
+            self.writer.write_all(self.prefix.as_bytes())?;
+            self.fresh = false;
+        }
+        Ok(())
+    }
+
+    /// Return true if the next event is inline content of the open block.
+    fn inline_follows(&mut self) -> bool {
+        matches!(
+            self.iter.peek(),
+            Some(md) if matches!(md, Md::Text(_)) || is_inline(md)
+        )
+    }
+
+    /// Return true if blocks are written without blank lines between them.
+    fn tight(&self) -> bool {
+        let mut containers = self.containers.iter().rev();
+
+        if let Some(Container::Item(_)) = containers.clone().next() {
+            containers.next();
+        }
+
+        matches!(
+            containers.next(),
+            Some(Container::List { loose: false, .. })
+        )
+    }
+
+    /// Open a list with items written as `marker` (after `number` if
+    /// ordered).
+    fn open_list(
+        &mut self,
+        marker: &'static str,
+        number: Option<u32>,
+    ) -> Result {
+        if !self.inline {
+            self.separate()?;
+        }
+        self.containers.push(Container::List {
+            marker,
+            number,
+            loose: false,
+        });
+        Ok(())
+    }
+
+    /// Open a block quote.
+    fn open_quote(&mut self) -> Result {
+        if self.inline {
+            self.writer.write_all(b"> ")?;
+        } else {
+            self.separate()?;
+        }
+        self.containers.push(Container::Quote(self.prefix.len()));
+        self.prefix.push_str("> ");
+        Ok(())
+    }
+
+    /// Write the kind of a GitHub alert, followed by any title on the same
+    /// line.
+    fn alert(&mut self, kind: &str) -> Result {
+        self.open(&format!("[!{}]", kind.to_ascii_uppercase()))?;
+        // The next line is body
+        if self.inline_follows() {
+            self.writer.write_all(b" ")?;
+        }
+        Ok(())
+    }
+
+    /// Write an admonition of `kind` and its title with MkDocs syntax,
+    /// indenting the blocks after it.
+    fn admonition(&mut self, kind: &str) -> Result {
+        self.open(&format!("!!! {}", kind.to_ascii_lowercase()))?;
+        // The title is closed with the block
+        if self.inline_follows() {
+            self.writer.write_all(b" \"")?;
+            self.open_title = true;
+        }
+        self.containers.push(Container::Quote(self.prefix.len()));
+        self.prefix.push_str("    ");
+        Ok(())
+    }
+
+    /// Write a details block and its summary, with `???` syntax if it has a
+    /// kind, indenting the blocks after it.
+    fn details(
+        &mut self,
+        kind: Option<Cow<'a, str>>,
+        summary: Cow<'a, str>,
+        expanded: bool,
+    ) -> Result {
+        let mut lines = vec![summary];
+
+        while let Some(Md::Text(_)) = self.iter.peek() {
+            if let Some(Md::Text(text)) = self.iter.next() {
+                lines.push(text);
+            }
+        }
+
+        if let Some(kind) = kind {
+            let marker = if expanded { "???+" } else { "???" };
+            let summary = lines.join(" ");
+
+            // Untitled details have the kind as summary
+            if summary == kind {
+                self.open(&format!("{marker} {kind}"))?;
+            } else {
+                self.open(&format!("{marker} {kind} \"{summary}\""))?;
+            }
+            self.containers.push(Container::Quote(self.prefix.len()));
+            self.prefix.push_str("    ");
+            return Ok(());
+        }
+
+        self.open(if expanded { "+++ " } else { "++  " })?;
+        self.writer.write_all(lines[0].as_bytes())?;
+        self.containers.push(Container::Quote(self.prefix.len()));
+        self.prefix.push_str("    ");
+        for line in &lines[1..] {
+            self.writer.write_all(b"\n")?;
+            self.writer.write_all(self.prefix.as_bytes())?;
+            self.writer.write_all(line.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Write a code block, indented if enabled and possible, or fenced.
+    fn code_block(&mut self, code: &str, after_list: bool) -> Result {
+        let info = self.syntax.take().unwrap_or_default();
+        let blank =
+            |line: Option<&str>| line.map_or(true, |l| l.trim().is_empty());
+
+        // Indented code has no info string, can't start or end with blank
+        // lines, and can't continue a paragraph in a tight list or the last
+        // item of a list
+        if self.indent_code
+            && info.is_empty()
+            && !after_list
+            && !blank(code.lines().next())
+            && !blank(code.lines().last())
+            && !self.tight()
+        {
+            self.open("")?;
+            for (i, line) in code.lines().enumerate() {
+                if i > 0 {
+                    self.writer.write_all(b"\n")?;
+                    if line.is_empty() {
+                        self.writer
+                            .write_all(self.prefix.trim_end().as_bytes())?;
+                        continue;
+                    }
+                    self.writer.write_all(self.prefix.as_bytes())?;
+                }
+                self.writer.write_all(b"    ")?;
+                self.writer.write_all(line.as_bytes())?;
+            }
+            return Ok(());
+        }
+
+        // Backticks can't be in the info string of their fence
+        let marker = if info.contains('`') { '~' } else { '`' };
+        // Fence must be longer than any run of it in the code
+        let longest =
+            code.split(|c| c != marker).map(str::len).max().unwrap_or(0);
+        let fence = marker.to_string().repeat(longest.max(2) + 1);
+
+        self.open(&fence)?;
+        self.writer.write_all(info.as_bytes())?;
+        for line in code.lines() {
+            self.writer.write_all(b"\n")?;
+            if line.is_empty() {
+                self.writer.write_all(self.prefix.trim_end().as_bytes())?;
+            } else {
+                self.writer.write_all(self.prefix.as_bytes())?;
+                self.writer.write_all(line.as_bytes())?;
+            }
+        }
+        self.writer.write_all(b"\n")?;
+        self.writer.write_all(self.prefix.as_bytes())?;
+        Ok(self.writer.write_all(fence.as_bytes())?)
+    }
+
+    /// End a table row, followed by the delimiter row after the header.
+    fn end_row(&mut self) -> Result {
+        self.writer.write_all(b" |")?;
+        if self.cell == self.table.len() {
+            self.writer.write_all(b"\n")?;
+            self.writer.write_all(self.prefix.as_bytes())?;
+            write!(self.writer, "| {} |", self.table.join(" | "))?;
+        }
+        Ok(())
+    }
+
+    /// Make the list loose if in a list item, since it contains a paragraph.
+    fn loosen(&mut self) {
+        if let [.., Container::List { loose, .. }, Container::Item(_)] =
+            self.containers.as_mut_slice()
+        {
+            *loose = true;
+        }
+    }
+
+    /// Close the open block and list item, if any, returning true if there
+    /// was an item.
+    fn close_item(&mut self) -> Result<bool> {
+        self.close()?;
+        let Some(Container::Item(len)) = self.containers.last() else {
+            return Ok(false);
+        };
+
+        self.prefix.truncate(*len);
+        self.containers.pop();
+        self.inline = false;
+        Ok(true)
+    }
+
+    /// Close the open block, underlining setext headings and ending tables.
+    fn close(&mut self) -> Result {
+        if !self.table.is_empty() {
+            if self.cell > 0 {
+                self.end_row()?;
+            }
+            self.table.clear();
+            self.cell = 0;
+        }
+
+        if mem::take(&mut self.open_title) {
+            self.writer.write_all(b"\"")?;
+        }
+
+        if let Some(id) = self.heading_id.take() {
+            write!(self.writer, " {{#{id}}}")?;
+            self.heading_width += id.chars().count() + 4;
+        }
+
+        let underline = if self.setext && self.open_h1 {
+            "="
+        } else if self.setext && self.open_h2 {
+            "-"
+        } else {
+            ""
+        };
+
+        if !underline.is_empty() {
+            self.writer.write_all(b"\n")?;
+            self.writer.write_all(self.prefix.as_bytes())?;
+            self.writer.write_all(
+                underline.repeat(self.heading_width.max(3)).as_bytes(),
+            )?;
+        }
+
+        self.open_paragraph = false;
+        self.open_h1 = false;
+        self.open_h2 = false;
+        self.open_h3 = false;
+        self.open_h4 = false;
+        self.open_h5 = false;
+        self.open_h6 = false;
+        self.heading_width = 0;
+        Ok(())
+    }
+}
+
+/// Return true for inline events other than text.
+pub(crate) fn is_inline(md: &Md<'_>) -> bool {
+    matches!(
+        md,
+        Md::Code(_)
+            | Md::Link(_)
+            | Md::Italic(_)
+            | Md::Bold(_)
+            | Md::BoldItalic(_)
+            | Md::Superscript(_)
+            | Md::Subscript(_)
+            | Md::Strikethrough(_)
+            | Md::Highlight(_)
+            | Md::Underline(_)
+            | Md::LinkRef(_)
+            | Md::ImageRef(_)
+            | Md::LinkNum(..)
+            | Md::ImageNum(..)
+            | Md::FootnoteRef(_)
+            | Md::LineBreak
+    )
 }
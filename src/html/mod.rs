@@ -1,11 +1,14 @@
 //! HTML encoding of markdown
 
 use std::{
+    borrow::Cow,
+    collections::HashMap,
     io::{self, Write},
-    result,
+    iter::Peekable,
+    mem, result,
 };
 
-use crate::Md;
+use crate::{encoder::is_inline, Md};
 
 /// `Result` type alias for convenience
 pub type Result<T = (), E = Error> = result::Result<T, E>;
@@ -24,7 +27,7 @@ impl From<io::Error> for Error {
 
 /// A markdown to HTML encoder
 pub struct HtmlEncoder<'a, W: Write> {
-    iter: Box<dyn Iterator<Item = Md<'a>> + 'a>,
+    iter: Peekable<Box<dyn Iterator<Item = Md<'a>> + 'a>>,
     writer: W,
     open_paragraph: bool,
     open_h1: bool,
@@ -33,14 +36,38 @@ pub struct HtmlEncoder<'a, W: Write> {
     open_h4: bool,
     open_h5: bool,
     open_h6: bool,
+    open_summary: bool,
+    open_figure: bool,
     last_text: bool,
+    /// Closing tags of each open block quote or alert
+    quotes: Vec<&'static str>,
+    /// Closing tags of each open list and its open item or term
+    lists: Vec<(&'static str, Option<&'static str>)>,
+    /// Task checkbox to write at the start of the item's text
+    task: Option<bool>,
+    /// Syntax highlighting for the following code block
+    language: Option<Cow<'a, str>>,
+    /// Alignment attributes of each column of the open table
+    table: Vec<&'static str>,
+    /// Number of table cells written
+    cell: usize,
+    /// Content of footnote definitions by lowercase label
+    footnotes: HashMap<String, Vec<Md<'a>>>,
+    /// Lowercase labels of referenced footnotes, in order of first reference
+    footnote_refs: Vec<String>,
+    /// Destination and any title of link reference definitions by
+    /// normalized label
+    links: HashMap<String, (Cow<'a, str>, Option<Cow<'a, str>>)>,
+    enable_tasks: bool,
+    enable_comments: bool,
 }
 
 impl<'a, W: Write> HtmlEncoder<'a, W> {
     /// Create a new HTML encoder
     pub fn new(iter: impl IntoIterator<Item = Md<'a>> + 'a, writer: W) -> Self {
         Self {
-            iter: Box::new(iter.into_iter()),
+            iter: (Box::new(iter.into_iter()) as Box<dyn Iterator<Item = _>>)
+                .peekable(),
             writer,
             open_paragraph: false,
             open_h1: false,
@@ -49,29 +76,54 @@ impl<'a, W: Write> HtmlEncoder<'a, W> {
             open_h4: false,
             open_h5: false,
             open_h6: false,
+            open_summary: false,
+            open_figure: false,
             last_text: false,
+            quotes: Vec::new(),
+            lists: Vec::new(),
+            task: None,
+            language: None,
+            table: Vec::new(),
+            cell: 0,
+            footnotes: HashMap::new(),
+            footnote_refs: Vec::new(),
+            links: HashMap::new(),
+            enable_tasks: false,
+            enable_comments: false,
         }
     }
 
+    /// Render task list checkboxes enabled, rather than disabled (the
+    /// default).
+    pub fn enable_tasks(mut self, enable: bool) -> Self {
+        self.enable_tasks = enable;
+        self
+    }
+
+    /// Render comments as HTML comments `<!-- -->`, rather than dropping
+    /// them (the default).
+    pub fn enable_comments(mut self, enable: bool) -> Self {
+        self.enable_comments = enable;
+        self
+    }
+
     /// Encode from the iterator some HTML
     pub fn encode_html(&mut self) -> Result {
-        fn close<W>(flag: &mut bool, text: &str, writer: &mut W) -> Result
-        where
-            W: Write,
-        {
-            if *flag {
-                *flag = false;
-                writer.write_all(text.as_bytes())?;
-            }
-
-            Ok(())
-        }
+        self.collect_definitions();
+        self.encode_blocks()?;
+        self.close_blocks()?;
+        self.encode_footnotes()
+    }
 
-        for md in &mut self.iter {
+    /// Encode blocks from the iterator.
+    fn encode_blocks(&mut self) -> Result {
+        while let Some(md) = self.iter.next() {
             let last_text = self.last_text;
 
             self.last_text = false;
 
+            // Table columns after cells start another table
+            let new_table = self.table.is_empty() || self.cell > 0;
             let mut open = |text: &str| -> Result {
                 close(&mut self.open_paragraph, "</p>", &mut self.writer)?;
                 close(&mut self.open_h1, "</h1>", &mut self.writer)?;
@@ -80,40 +132,385 @@ impl<'a, W: Write> HtmlEncoder<'a, W> {
                 close(&mut self.open_h4, "</h4>", &mut self.writer)?;
                 close(&mut self.open_h5, "</h5>", &mut self.writer)?;
                 close(&mut self.open_h6, "</h6>", &mut self.writer)?;
+                close(&mut self.open_summary, "</summary>", &mut self.writer)?;
+                close(
+                    &mut self.open_figure,
+                    "</figcaption></figure>",
+                    &mut self.writer,
+                )?;
+                close_table(&mut self.table, &mut self.cell, &mut self.writer)?;
                 // FIXME: Escape HTML
                 Ok(self.writer.write_all(text.as_bytes())?)
             };
 
             match md {
                 Md::Paragraph => {
-                    open("<p>")?;
-                    self.open_paragraph = true;
+                    // Paragraphs starting with a captioned image are figures
+                    let image = self.iter.next_if(|md| {
+                        matches!(md, Md::ImageRef(_) | Md::ImageNum(..))
+                    });
+                    let label =
+                        image.as_ref().and_then(|_| label(&mut self.iter));
+                    let link = image
+                        .as_ref()
+                        .and_then(|_| destination(&mut self.iter));
+                    let figure = image.is_some()
+                        && self.iter.next_if(|md| *md == Md::Caption).is_some();
+
+                    open(if figure { "<figure>" } else { "<p>" })?;
+                    self.open_paragraph = !figure;
+                    self.open_figure = figure;
+                    self.write_task()?;
+                    match image {
+                        Some(Md::ImageRef(alt)) => {
+                            self.link(alt, "!", label, link)?
+                        }
+                        Some(Md::ImageNum(alt, number)) => {
+                            let label = number.to_string().into();
+
+                            self.link(alt, "!", Some(label), link)?
+                        }
+                        _ => {}
+                    }
+                    if figure {
+                        self.writer.write_all(b"<figcaption>")?;
+                    }
+                }
+                Md::LineBreak => {
+                    self.write_task()?;
+                    self.writer.write_all(b"<br>")?;
+                }
+                Md::Italic(open) => {
+                    self.write_task()?;
+                    self.writer.write_all(if open {
+                        b"<em>"
+                    } else {
+                        b"</em>"
+                    })?;
+                }
+                Md::Bold(open) => {
+                    self.write_task()?;
+                    self.writer.write_all(if open {
+                        b"<strong>"
+                    } else {
+                        b"</strong>"
+                    })?;
+                }
+                Md::BoldItalic(open) => {
+                    self.write_task()?;
+                    self.writer.write_all(if open {
+                        b"<em><strong>"
+                    } else {
+                        b"</strong></em>"
+                    })?;
+                }
+                Md::Superscript(open) => {
+                    self.write_task()?;
+                    self.writer.write_all(if open {
+                        b"<sup>"
+                    } else {
+                        b"</sup>"
+                    })?;
+                }
+                Md::Subscript(open) => {
+                    self.write_task()?;
+                    self.writer.write_all(if open {
+                        b"<sub>"
+                    } else {
+                        b"</sub>"
+                    })?;
+                }
+                Md::Strikethrough(open) => {
+                    self.write_task()?;
+                    self.writer.write_all(if open {
+                        b"<del>"
+                    } else {
+                        b"</del>"
+                    })?;
+                }
+                Md::Highlight(open) => {
+                    self.write_task()?;
+                    self.writer.write_all(if open {
+                        b"<mark>"
+                    } else {
+                        b"</mark>"
+                    })?;
                 }
+                Md::Underline(open) => {
+                    self.write_task()?;
+                    self.writer.write_all(if open {
+                        b"<ins>"
+                    } else {
+                        b"</ins>"
+                    })?;
+                }
+                Md::Link(link) => {
+                    self.write_task()?;
+                    write!(
+                        self.writer,
+                        "<a href=\"{}\">{}</a>",
+                        escape(&autolink_href(&link)),
+                        escape(&link),
+                    )?;
+                }
+                Md::Code(code) => {
+                    self.write_task()?;
+                    write!(self.writer, "<code>{}</code>", escape(&code))?;
+                }
+                Md::Caption | Md::FrontMatter(_) => {}
+                // Definitions are collected before, and stray parts of them
+                // or of headings have nothing to write
+                Md::LinkKey(_)
+                | Md::LinkLabel(_)
+                | Md::LinkVal(_)
+                | Md::Title(_)
+                | Md::FootnoteOpen(_)
+                | Md::FootnoteClose
+                | Md::HeadingId(_) => {}
                 Md::Heading1 => {
-                    open("<h1>")?;
+                    open(&heading("h1", self.iter.next_if(is_heading_id)))?;
                     self.open_h1 = true;
                 }
                 Md::Heading2 => {
-                    open("<h2>")?;
+                    open(&heading("h2", self.iter.next_if(is_heading_id)))?;
                     self.open_h2 = true;
                 }
                 Md::Heading3 => {
-                    open("<h3>")?;
+                    open(&heading("h3", self.iter.next_if(is_heading_id)))?;
                     self.open_h3 = true;
                 }
                 Md::Heading4 => {
-                    open("<h4>")?;
+                    open(&heading("h4", self.iter.next_if(is_heading_id)))?;
                     self.open_h4 = true;
                 }
                 Md::Heading5 => {
-                    open("<h5>")?;
+                    open(&heading("h5", self.iter.next_if(is_heading_id)))?;
                     self.open_h5 = true;
                 }
                 Md::Heading6 => {
-                    open("<h6>")?;
+                    open(&heading("h6", self.iter.next_if(is_heading_id)))?;
                     self.open_h6 = true;
                 }
+                Md::HorizontalRule => open("<hr>")?,
+                Md::Comment(comment) => {
+                    if self.enable_comments {
+                        open("<!-- ")?;
+                        self.writer.write_all(
+                            comment.replace("-->", "--&gt;").as_bytes(),
+                        )?;
+                        self.writer.write_all(b" -->")?;
+                    }
+                }
+                Md::QuoteOpen => {
+                    // Block quotes starting with an admonition are alerts, and
+                    // with a summary (after any kind) are details
+                    let kind = match self
+                        .iter
+                        .next_if(|md| matches!(md, Md::Admonition(_)))
+                    {
+                        Some(Md::Admonition(kind)) => Some(kind),
+                        _ => None,
+                    };
+
+                    if let Some(Md::Details(_, expanded)) = self.iter.peek() {
+                        let class = kind
+                            .map(|kind| {
+                                format!(
+                                    " class=\"{}\"",
+                                    escape(&kind.to_ascii_lowercase()),
+                                )
+                            })
+                            .unwrap_or_default();
+                        let expanded = if *expanded { " open" } else { "" };
+
+                        open(&format!("<details{class}{expanded}>"))?;
+                        self.quotes.push("</details>");
+                    } else if let Some(kind) = kind {
+                        open(&format!(
+                            "<div class=\"markdown-alert markdown-alert-{}\">",
+                            escape(&kind.to_ascii_lowercase()),
+                        ))?;
+                        self.quotes.push("</div>");
+                        self.alert_title(&kind)?;
+                    } else {
+                        open("<blockquote>")?;
+                        self.quotes.push("</blockquote>");
+                    }
+                }
+                Md::QuoteClose => {
+                    open(self.quotes.pop().unwrap_or("</blockquote>"))?
+                }
+                Md::Admonition(kind) => {
+                    open("")?;
+                    self.alert_title(&kind)?;
+                }
+                Md::Details(summary, _) => {
+                    open("<summary>")?;
+                    self.writer.write_all(summary.as_bytes())?;
+                    self.open_summary = true;
+                    self.last_text = true;
+                }
+                Md::UnorderedList => {
+                    open("<ul>")?;
+                    self.lists.push(("</ul>", None));
+                }
+                Md::OrderedList => {
+                    open("<ol>")?;
+                    self.lists.push(("</ol>", None));
+                }
+                Md::OrderedListStart(number, _) => {
+                    if number == 1 {
+                        open("<ol>")?;
+                    } else {
+                        open(&format!("<ol start=\"{number}\">"))?;
+                    }
+                    self.lists.push(("</ol>", None));
+                }
+                Md::DefinitionList => {
+                    open("<dl>")?;
+                    self.lists.push(("</dl>", None));
+                }
+                Md::DefinitionTerm => {
+                    let close = self.lists.last_mut().and_then(|l| l.1.take());
+
+                    open(close.unwrap_or(""))?;
+                    self.writer.write_all(b"<dt>")?;
+                    if let Some(list) = self.lists.last_mut() {
+                        list.1 = Some("</dt>");
+                    }
+                }
+                Md::ListItem => {
+                    let close = self.lists.last_mut().and_then(|l| l.1.take());
+                    let (item, close_item) = match self.lists.last() {
+                        Some(("</dl>", _)) => ("<dd>", "</dd>"),
+                        _ => ("<li>", "</li>"),
+                    };
+
+                    open(close.unwrap_or(""))?;
+                    self.writer.write_all(item.as_bytes())?;
+                    if let Some(list) = self.lists.last_mut() {
+                        list.1 = Some(close_item);
+                    }
+                }
+                Md::ListClose => {
+                    let (close_list, close_item) =
+                        self.lists.pop().unwrap_or(("", None));
+
+                    open(close_item.unwrap_or(""))?;
+                    self.writer.write_all(close_list.as_bytes())?;
+                }
+                Md::ListTask(checked) => self.task = Some(checked),
+                Md::TableLeft | Md::TableCentered | Md::TableRight => {
+                    if new_table {
+                        open("<table>")?;
+                    }
+                    self.table.push(match md {
+                        Md::TableCentered => " style=\"text-align: center\"",
+                        Md::TableRight => " style=\"text-align: right\"",
+                        _ => "",
+                    });
+                }
+                Md::TableCell => {
+                    let columns = self.table.len().max(1);
+                    let (row, column) =
+                        (self.cell / columns, self.cell % columns);
+
+                    if self.cell > 0 {
+                        self.writer.write_all(if self.cell <= columns {
+                            b"</th>"
+                        } else {
+                            b"</td>"
+                        })?;
+                    }
+                    if column == 0 {
+                        self.writer.write_all(match row {
+                            0 => b"<thead><tr>",
+                            1 => b"</tr></thead><tbody><tr>",
+                            _ => b"</tr><tr>",
+                        })?;
+                    }
+
+                    let align = self.table.get(column).unwrap_or(&"");
+
+                    if row == 0 {
+                        write!(self.writer, "<th{align}>")?;
+                    } else {
+                        write!(self.writer, "<td{align}>")?;
+                    }
+                    self.cell += 1;
+                }
+                Md::FootnoteRef(label) => {
+                    let key = label.to_lowercase();
+
+                    self.write_task()?;
+                    // Undefined footnotes stay as text
+                    if !self.footnotes.contains_key(&key) {
+                        write!(self.writer, "[^{label}]")?;
+                        continue;
+                    }
+
+                    let number =
+                        self.footnote_refs.iter().position(|k| *k == key);
+                    let id = escape(&key);
+
+                    match number {
+                        Some(number) => write!(
+                            self.writer,
+                            "<sup><a href=\"#fn-{id}\">{}</a></sup>",
+                            number + 1,
+                        )?,
+                        None => {
+                            write!(
+                                self.writer,
+                                "<sup><a href=\"#fn-{id}\" id=\"fnref-{id}\">{}</a></sup>",
+                                self.footnote_refs.len() + 1,
+                            )?;
+                            self.footnote_refs.push(key);
+                        }
+                    }
+                }
+                Md::LinkRef(text) => {
+                    let label = label(&mut self.iter);
+                    let link = destination(&mut self.iter);
+
+                    self.link(text, "", label, link)?
+                }
+                Md::ImageRef(alt) => {
+                    let label = label(&mut self.iter);
+                    let link = destination(&mut self.iter);
+
+                    self.link(alt, "!", label, link)?
+                }
+                Md::LinkNum(text, number) => {
+                    let link = destination(&mut self.iter);
+
+                    self.link(text, "", Some(number.to_string().into()), link)?
+                }
+                Md::ImageNum(alt, number) => {
+                    let link = destination(&mut self.iter);
+
+                    self.link(alt, "!", Some(number.to_string().into()), link)?
+                }
+                Md::SyntaxHighlighting(info) => self.language = Some(info),
+                Md::Codeblock(code) => {
+                    // Only the first word of the info string is the language
+                    match self
+                        .language
+                        .take()
+                        .as_deref()
+                        .and_then(|info| info.split_whitespace().next())
+                    {
+                        Some(language) => open(&format!(
+                            "<pre><code class=\"language-{}\">",
+                            escape(language),
+                        ))?,
+                        None => open("<pre><code>")?,
+                    }
+                    self.writer.write_all(escape(&code).as_bytes())?;
+                    self.writer.write_all(b"</code></pre>")?;
+                }
                 Md::Text(text) => {
+                    self.write_task()?;
                     if last_text {
                         self.writer.write_all(b" ")?;
                     }
@@ -121,10 +518,14 @@ impl<'a, W: Write> HtmlEncoder<'a, W> {
                     self.writer.write_all(text.as_bytes())?;
                     self.last_text = true;
                 }
-                _ => unimplemented!(),
             }
         }
 
+        Ok(())
+    }
+
+    /// Close the open block.
+    fn close_blocks(&mut self) -> Result {
         close(&mut self.open_paragraph, "</p>", &mut self.writer)?;
         close(&mut self.open_h1, "</h1>", &mut self.writer)?;
         close(&mut self.open_h2, "</h2>", &mut self.writer)?;
@@ -132,6 +533,314 @@ impl<'a, W: Write> HtmlEncoder<'a, W> {
         close(&mut self.open_h4, "</h4>", &mut self.writer)?;
         close(&mut self.open_h5, "</h5>", &mut self.writer)?;
         close(&mut self.open_h6, "</h6>", &mut self.writer)?;
+        close(&mut self.open_summary, "</summary>", &mut self.writer)?;
+        close(
+            &mut self.open_figure,
+            "</figcaption></figure>",
+            &mut self.writer,
+        )?;
+        close_table(&mut self.table, &mut self.cell, &mut self.writer)?;
+        Ok(())
+    }
+
+    /// Take footnote definitions out of the iterator, to write them after
+    /// the document, and link reference definitions, to resolve references.
+    fn collect_definitions(&mut self) {
+        let mut mds = Vec::new();
+        let mut open: Vec<(Cow<'a, str>, Vec<Md<'a>>)> = Vec::new();
+
+        while let Some(md) = self.iter.next() {
+            // Footnotes defined within footnotes are separate definitions
+            match md {
+                // Links may be defined within footnotes too
+                Md::LinkKey(label) => {
+                    let destination = match self.iter.next_if(is_destination) {
+                        Some(Md::LinkVal(destination)) => destination,
+                        _ => Cow::Borrowed(""),
+                    };
+                    let title = match self.iter.next_if(is_title) {
+                        Some(Md::Title(title)) => Some(title),
+                        _ => None,
+                    };
+
+                    self.links
+                        .entry(normalize_label(&label))
+                        .or_insert((destination, title));
+                }
+                Md::FootnoteOpen(label) => open.push((label, Vec::new())),
+                Md::FootnoteClose => {
+                    if let Some((label, content)) = open.pop() {
+                        self.footnotes
+                            .entry(label.to_lowercase())
+                            .or_insert(content);
+                    }
+                }
+                md => match open.last_mut() {
+                    Some((_, content)) => content.push(md),
+                    None => mds.push(md),
+                },
+            }
+        }
+
+        // Unclosed footnotes end with the document
+        while let Some((label, content)) = open.pop() {
+            self.footnotes
+                .entry(label.to_lowercase())
+                .or_insert(content);
+        }
+
+        self.iter = (Box::new(mds.into_iter()) as Box<dyn Iterator<Item = _>>)
+            .peekable();
+    }
+
+    /// Write the referenced footnotes, numbered in order of first reference,
+    /// with links back to it.
+    fn encode_footnotes(&mut self) -> Result {
+        if self.footnote_refs.is_empty() {
+            return Ok(());
+        }
+
+        self.writer
+            .write_all(b"<section class=\"footnotes\"><ol>")?;
+
+        // Footnotes may reference more footnotes
+        let mut index = 0;
+
+        while let Some(key) = self.footnote_refs.get(index).cloned() {
+            let content = self.footnotes.remove(&key).unwrap_or_default();
+            let id = escape(&key);
+            let back = format!("<a href=\"#fnref-{id}\">↩</a>");
+
+            write!(self.writer, "<li id=\"fn-{id}\">")?;
+            self.iter = (Box::new(content.into_iter())
+                as Box<dyn Iterator<Item = _>>)
+                .peekable();
+            self.encode_blocks()?;
+            // Link back at the end of the last paragraph
+            if mem::take(&mut self.open_paragraph) {
+                write!(self.writer, " {back}</p>")?;
+            } else {
+                self.close_blocks()?;
+                self.writer.write_all(back.as_bytes())?;
+            }
+            self.close_blocks()?;
+            self.writer.write_all(b"</li>")?;
+            index += 1;
+        }
+
+        Ok(self.writer.write_all(b"</ol></section>")?)
+    }
+
+    /// Write a link (or image, after `!`) with the destination and any title
+    /// that followed it, or else of the definition for its label or text.
+    fn link(
+        &mut self,
+        text: Cow<'a, str>,
+        marker: &str,
+        label: Option<Cow<'a, str>>,
+        link: Option<(Cow<'a, str>, Option<Cow<'a, str>>)>,
+    ) -> Result {
+        self.write_task()?;
+
+        let link = link.or_else(|| {
+            let key = normalize_label(label.as_deref().unwrap_or(&text));
+
+            self.links.get(&key).cloned()
+        });
+
+        // Undefined references stay as text
+        let Some((destination, title)) = link else {
+            write!(self.writer, "{marker}[{text}]")?;
+            if let Some(label) = label {
+                write!(self.writer, "[{label}]")?;
+            }
+            return Ok(());
+        };
+        let destination = escape(&destination);
+        let title = title
+            .map(|title| format!(" title=\"{}\"", escape(&title)))
+            .unwrap_or_default();
+
+        if marker.is_empty() {
+            write!(self.writer, "<a href=\"{destination}\"{title}>{text}</a>")?;
+        } else {
+            write!(
+                self.writer,
+                "<img src=\"{destination}\" alt=\"{}\"{title}>",
+                escape(&text),
+            )?;
+        }
+
         Ok(())
     }
+
+    /// Write the title of an alert, the text following it or else the
+    /// capitalized kind.
+    fn alert_title(&mut self, kind: &str) -> Result {
+        self.writer
+            .write_all(b"<p class=\"markdown-alert-title\">")?;
+        if matches!(
+            self.iter.peek(),
+            Some(md) if matches!(md, Md::Text(_)) || is_inline(md)
+        ) {
+            self.open_paragraph = true;
+            return Ok(());
+        }
+
+        // Default title is the capitalized kind
+        let kind = kind.to_ascii_lowercase();
+        let mut chars = kind.chars();
+        let title: String = chars
+            .next()
+            .map(|c| c.to_ascii_uppercase())
+            .into_iter()
+            .chain(chars)
+            .collect();
+
+        Ok(write!(self.writer, "{}</p>", escape(&title))?)
+    }
+
+    /// Write the pending task list checkbox, if any.
+    fn write_task(&mut self) -> Result {
+        let Some(checked) = self.task.take() else {
+            return Ok(());
+        };
+        let checked = if checked { " checked" } else { "" };
+        let disabled = if self.enable_tasks { "" } else { " disabled" };
+
+        Ok(write!(
+            self.writer,
+            "<input type=\"checkbox\"{checked}{disabled}> "
+        )?)
+    }
+}
+
+/// Return the destination of an autolink, with `mailto:` for email addresses
+/// and `http://` for `www.` links.
+fn autolink_href(link: &str) -> Cow<'_, str> {
+    if link.starts_with("www.") {
+        Cow::Owned(format!("http://{link}"))
+    } else if link.contains('@') && !link.contains(':') {
+        Cow::Owned(format!("mailto:{link}"))
+    } else {
+        Cow::Borrowed(link)
+    }
+}
+
+/// Return the opening tag of a heading, with any custom ID.
+fn heading(tag: &str, id: Option<Md<'_>>) -> String {
+    match id {
+        Some(Md::HeadingId(id)) => format!("<{tag} id=\"{}\">", escape(&id)),
+        _ => format!("<{tag}>"),
+    }
+}
+
+/// Return true for a custom heading ID.
+fn is_heading_id(md: &Md<'_>) -> bool {
+    matches!(md, Md::HeadingId(_))
+}
+
+/// Take the link reference label next from the iterator.
+fn label<'a>(
+    iter: &mut Peekable<impl Iterator<Item = Md<'a>>>,
+) -> Option<Cow<'a, str>> {
+    match iter.next_if(|md| matches!(md, Md::LinkLabel(_))) {
+        Some(Md::LinkLabel(label)) => Some(label),
+        _ => None,
+    }
+}
+
+/// Take the link destination and any title next from the iterator.
+fn destination<'a>(
+    iter: &mut Peekable<impl Iterator<Item = Md<'a>>>,
+) -> Option<(Cow<'a, str>, Option<Cow<'a, str>>)> {
+    let Some(Md::LinkVal(destination)) = iter.next_if(is_destination) else {
+        return None;
+    };
+    let title = match iter.next_if(is_title) {
+        Some(Md::Title(title)) => Some(title),
+        _ => None,
+    };
+
+    Some((destination, title))
+}
+
+/// Return true for a link destination.
+fn is_destination(md: &Md<'_>) -> bool {
+    matches!(md, Md::LinkVal(_))
+}
+
+/// Return true for a link title.
+fn is_title(md: &Md<'_>) -> bool {
+    matches!(md, Md::Title(_))
+}
+
+/// Return the link label, case-insensitive and with whitespace collapsed.
+fn normalize_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Escape characters with special meaning in HTML text and attributes.
+fn escape(text: &str) -> Cow<'_, str> {
+    if !text.contains(['&', '<', '>', '"']) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut escaped = String::with_capacity(text.len() + 16);
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+
+    Cow::Owned(escaped)
+}
+
+/// Write the closing tag `text` if `flag` is set, and clear it.
+fn close<W>(flag: &mut bool, text: &str, writer: &mut W) -> Result
+where
+    W: Write,
+{
+    if *flag {
+        *flag = false;
+        writer.write_all(text.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn close_table<W>(
+    table: &mut Vec<&'static str>,
+    cell: &mut usize,
+    writer: &mut W,
+) -> Result
+where
+    W: Write,
+{
+    if table.is_empty() {
+        return Ok(());
+    }
+
+    let columns = table.len();
+
+    if *cell > 0 {
+        let header = *cell <= columns;
+
+        writer.write_all(if header { b"</th>" } else { b"</td>" })?;
+        writer.write_all(b"</tr>")?;
+        writer.write_all(if header { b"</thead>" } else { b"</tbody>" })?;
+    }
+    writer.write_all(b"</table>")?;
+    table.clear();
+    *cell = 0;
+    Ok(())
 }
@@ -12,8 +12,13 @@ pub(crate) struct LineReader<'a>(
 
 impl<'a> LineReader<'a> {
     pub(crate) fn from_slice(md: &'a [u8]) -> Self {
+        // Split like `BufRead::lines()`, without an empty last line
         Self(Box::new(
-            md.split(|x| *x == b'\n').map(Cow::<'a, [u8]>::from).map(Ok),
+            md.split_inclusive(|x| *x == b'\n')
+                .map(|line| line.strip_suffix(b"\n").unwrap_or(line))
+                .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+                .map(Cow::<'a, [u8]>::from)
+                .map(Ok),
         ))
     }
 
@@ -29,20 +29,28 @@ pub enum Md<'a> {
     QuoteOpen,
     /// Same as above, close a quote block
     QuoteClose,
-    /// Open an ordered list, with any of `1234567890`, followed by `. `
+    /// Open an ordered list starting at 1, with any of `1234567890`, followed
+    /// by `. `
     OrderedList,
+    /// Open an ordered list starting at a number other than 1, or with items
+    /// followed by `) ` (start number, and `.` or `)` delimiter)
+    OrderedListStart(u32, char),
     /// Open an unordered list, with any of `-+*`, followed by ` `
     UnorderedList,
-    /// Definition list (followed by term as `Text`, then `ListItem` which is
-    /// any following line starting with `: `)
+    /// Definition list (followed by `DefinitionTerm`s, then `ListItem` which
+    /// is any following line starting with `: `)
     DefinitionList,
+    /// Definition list term (followed by term as `Text`), any line before the
+    /// first `ListItem` of a group
+    DefinitionTerm,
     /// List item, continues item as long as indented 4 spaces or tab
     ListItem,
     /// In an unordered list, `[x] ` or `[ ] `
     ListTask(bool),
     /// Close a list
     ListClose,
-    /// When image is not followed by a new paragraph
+    /// When image is not followed by a new paragraph, between an image
+    /// starting a paragraph and the caption text after it
     Caption,
     /// Italic style text, `*` or `_`
     Italic(bool),
@@ -60,6 +68,12 @@ pub enum Md<'a> {
     Highlight(bool),
     /// Underline `--`, `<ins></ins>`, `<u></u>`
     Underline(bool),
+    /// Admonition kind, starting a block quote and followed by any title text
+    /// (or by `Details`, giving the kind of `???` details)
+    ///
+    /// Like GitHub, the title is the kind, and the lines after it are the
+    /// body.
+    ///
     /// Same line warning text:
     ///
     /// ```md
@@ -99,6 +113,13 @@ pub enum Md<'a> {
     /// Some more information about the warning
     /// ```
     ///
+    /// Title text on the same line replaces the kind, and ends with the line:
+    ///
+    /// ```md
+    /// > [!WARNING] My warning title
+    /// > Some more information about the warning
+    /// ```
+    ///
     /// Alternative syntax:
     ///
     /// ```md
@@ -118,7 +139,7 @@ pub enum Md<'a> {
     /// Or with `!!!` syntax
     ///
     ///  - `note`
-    ///  - `astract`
+    ///  - `abstract`
     ///  - `info`
     ///  - `tip`
     ///  - `success`
@@ -130,6 +151,9 @@ pub enum Md<'a> {
     ///  - `example`
     ///  - `quote`
     Admonition(Cow<'a, str>),
+    /// Details summary (or kind, if untitled) and whether expanded, starting a
+    /// block quote and followed by any continued summary text
+    ///
     /// Unexpanded:
     ///
     /// ```md
@@ -150,7 +174,7 @@ pub enum Md<'a> {
     ///
     /// `<details><summary>My summary</summary></details>`
     ///
-    /// Alternative syntax unexpanded:
+    /// Alternative syntax, after an `Admonition` with the kind, unexpanded:
     ///
     /// ```md
     /// ??? info "My summary"
@@ -166,9 +190,10 @@ pub enum Md<'a> {
     ///     Details that can be collapsed
     /// ```
     Details(Cow<'a, str>, bool),
-    /// `[Some comment text]: #`
+    /// `[Some comment text]: #`, or `<!-- Some comment text -->` when HTML
+    /// comments are enabled
     Comment(Cow<'a, str>),
-    /// Open and close with one to two backticks
+    /// Inline code, open and close with runs of the same number of backticks
     Code(Cow<'a, str>),
     /// Open and close with tripple+ backtick or `~`, or 4 spaces
     Codeblock(Cow<'a, str>),
@@ -180,12 +205,16 @@ pub enum Md<'a> {
     ImageNum(Cow<'a, str>, u16),
     /// Image alt text, file referenced by alt text
     ImageRef(Cow<'a, str>),
-    /// Link text `<https://example.org>` or `https://example.org`
+    /// Link text `<https://example.org>`, `<me@example.org>` or bare
+    /// `https://example.org`, `www.example.org` and `me@example.org`
     Link(Cow<'a, str>),
     /// Link text with number `[My link][1]`
     LinkNum(Cow<'a, str>, u16),
     /// Link reference `[My link]`
     LinkRef(Cow<'a, str>),
+    /// Link reference label `[label]`, directly following a `LinkRef` or
+    /// `ImageRef` written as `[My link][label]`
+    LinkLabel(Cow<'a, str>),
     /// Link definition key `[My link]: https://example.org` or
     /// `[1]: https://example.org`
     ///
@@ -212,14 +241,23 @@ pub enum Md<'a> {
     FootnoteOpen(Cow<'a, str>),
     /// No longer indented, footnote has ended
     FootnoteClose,
-    /// Custom ID for heading `# Heading {#custom-id}`
+    /// Custom ID for heading `# Heading {#custom-id}`, directly after the
+    /// heading
     HeadingId(Cow<'a, str>),
-    /// Start table column `|` or start table align left `:---`
+    /// Start table column `---` or start table align left `:---`
+    ///
+    /// A table starts with one column event for each of its columns, followed
+    /// by its cells.
     TableLeft,
-    /// Start table column `|` or start table align center `:---:`
+    /// Start table column aligned center `:---:`
     TableCentered,
-    /// Start table column `|` or start table align right `---:`
+    /// Start table column aligned right `---:`
     TableRight,
-    /// Next cell in row, `|` or next column if end of row
+    /// Next cell in row, `|` or next row after a cell for each column, with
+    /// the first row being the header
     TableCell,
+    /// Front matter at the very beginning of the document, the raw block
+    /// with its `---` (YAML) or `+++` (TOML) delimiter lines, each ending in
+    /// a newline (the opening delimiter not followed by a blank line)
+    FrontMatter(Cow<'a, str>),
 }
@@ -3,7 +3,10 @@ use std::{
     str,
 };
 
-use marksad::{decode::Result, Decoder, Encoder, Md};
+use marksad::{
+    decode::{Error, Result, WarningKind},
+    Decoder, Encoder, Md,
+};
 
 fn test_reader_string(path: &str, f: impl Fn(&[Md<'_>])) {
     let file = File::open(path).unwrap();
@@ -144,3 +147,1587 @@ fn roundtrip() {
         });
     }
 }
+
+#[test]
+fn setext_headings() {
+    test_reader_string("tests/data/SETEXT_HEADINGS.md", |mds| {
+        let expected = [
+            Md::Heading1,
+            Md::Text("Heading 1".into()),
+            Md::Heading2,
+            Md::Text("Heading 2".into()),
+            Md::Heading1,
+            Md::Text("Some".into()),
+            Md::Text("paragraph".into()),
+            Md::Paragraph,
+            Md::Text("Not a heading".into()),
+            Md::Paragraph,
+            Md::Text("===".into()),
+        ];
+
+        assert_eq!(mds, expected);
+    });
+}
+
+#[test]
+fn roundtrip_setext() {
+    let headings =
+        fs::read_to_string("tests/data/ALL_SETEXT_HEADINGS.md").unwrap();
+
+    test_reader_string("tests/data/SETEXT_HEADINGS.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .setext(true)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), headings);
+    });
+}
+
+#[test]
+fn thematic_breaks() {
+    test_reader_string("tests/data/THEMATIC_BREAKS.md", |mds| {
+        let expected = [
+            Md::Paragraph,
+            Md::Text("Paragraph".into()),
+            Md::HorizontalRule,
+            Md::HorizontalRule,
+            Md::HorizontalRule,
+            Md::Paragraph,
+            Md::Text("Text".into()),
+            Md::HorizontalRule,
+            Md::Heading2,
+            Md::Text("Heading".into()),
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let breaks =
+        fs::read_to_string("tests/data/ALL_THEMATIC_BREAKS.md").unwrap();
+
+    test_reader_string("tests/data/THEMATIC_BREAKS.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), breaks);
+    });
+}
+
+#[test]
+fn quotes() {
+    test_reader_string("tests/data/QUOTES.md", |mds| {
+        let expected = [
+            Md::QuoteOpen,
+            Md::Heading1,
+            Md::Text("Quote heading".into()),
+            Md::Paragraph,
+            Md::Text("Quote text".into()),
+            Md::Text("lazy continuation".into()),
+            Md::QuoteOpen,
+            Md::Paragraph,
+            Md::Text("Nested quote".into()),
+            Md::Text("text".into()),
+            Md::QuoteClose,
+            Md::Paragraph,
+            Md::Text("Back out".into()),
+            Md::QuoteClose,
+            Md::Paragraph,
+            Md::Text("Paragraph".into()),
+            Md::QuoteOpen,
+            Md::Paragraph,
+            Md::Text("Quote interrupts paragraph".into()),
+            Md::QuoteOpen,
+            Md::Paragraph,
+            Md::Text("Doubly nested".into()),
+            Md::QuoteClose,
+            Md::QuoteClose,
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let quotes = fs::read_to_string("tests/data/ALL_QUOTES.md").unwrap();
+
+    test_reader_string("tests/data/QUOTES.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), quotes);
+    });
+}
+
+#[test]
+fn lists() {
+    test_reader_string("tests/data/LISTS.md", |mds| {
+        let expected = [
+            Md::UnorderedList,
+            Md::ListItem,
+            Md::Text("Tight item".into()),
+            Md::ListItem,
+            Md::Text("Another".into()),
+            Md::UnorderedList,
+            Md::ListItem,
+            Md::Text("Nested with tab".into()),
+            Md::ListItem,
+            Md::Text("Nested again".into()),
+            Md::ListClose,
+            Md::ListClose,
+            Md::UnorderedList,
+            Md::ListItem,
+            Md::Text("New list, new bullet".into()),
+            Md::ListClose,
+            Md::UnorderedList,
+            Md::ListItem,
+            Md::Paragraph,
+            Md::Text("Loose item".into()),
+            Md::ListItem,
+            Md::Paragraph,
+            Md::Text("Second paragraph item".into()),
+            Md::Paragraph,
+            Md::Text("Continued item".into()),
+            Md::Text("lazy text".into()),
+            Md::ListClose,
+            Md::Paragraph,
+            Md::Text("Paragraph".into()),
+            Md::UnorderedList,
+            Md::ListItem,
+            Md::UnorderedList,
+            Md::ListItem,
+            Md::UnorderedList,
+            Md::ListItem,
+            Md::ListClose,
+            Md::ListClose,
+            Md::ListClose,
+            Md::UnorderedList,
+            Md::ListItem,
+            Md::Text("Empty marker line".into()),
+            Md::ListItem,
+            Md::Text("Tight".into()),
+            Md::ListClose,
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let lists = fs::read_to_string("tests/data/ALL_LISTS.md").unwrap();
+
+    for path in ["tests/data/LISTS.md", "tests/data/ALL_LISTS.md"] {
+        test_reader_string(path, |mds| {
+            let mut bytes = Vec::new();
+
+            Encoder::new(mds.iter().cloned(), &mut bytes)
+                .encode_md()
+                .unwrap();
+            assert_eq!(str::from_utf8(&bytes).unwrap(), lists);
+        });
+    }
+}
+
+#[test]
+fn ordered_lists() {
+    test_reader_string("tests/data/ORDERED_LISTS.md", |mds| {
+        let expected = [
+            Md::OrderedListStart(7, '.'),
+            Md::ListItem,
+            Md::Text("Seven".into()),
+            Md::ListItem,
+            Md::Text("Eight".into()),
+            Md::OrderedListStart(1, ')'),
+            Md::ListItem,
+            Md::Text("Inner".into()),
+            Md::ListItem,
+            Md::Text("Inner".into()),
+            Md::ListClose,
+            Md::ListClose,
+            Md::Paragraph,
+            Md::Text("Paragraph".into()),
+            Md::OrderedList,
+            Md::ListItem,
+            Md::Text("Interrupts".into()),
+            Md::ListClose,
+            Md::Paragraph,
+            Md::Text("Text".into()),
+            Md::Text("2. No interrupt".into()),
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let lists = fs::read_to_string("tests/data/ALL_ORDERED_LISTS.md").unwrap();
+
+    test_reader_string("tests/data/ORDERED_LISTS.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), lists);
+    });
+}
+
+#[test]
+fn tasks() {
+    test_reader_string("tests/data/TASKS.md", |mds| {
+        let expected = [
+            Md::UnorderedList,
+            Md::ListItem,
+            Md::ListTask(false),
+            Md::Text("Todo".into()),
+            Md::ListItem,
+            Md::ListTask(true),
+            Md::Text("Done".into()),
+            Md::ListItem,
+            Md::Text("[ ]".into()),
+            Md::ListItem,
+            Md::ListTask(true),
+            Md::Text("Spaced".into()),
+            Md::ListClose,
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let toggled = fs::read_to_string("tests/data/TOGGLED_TASKS.md").unwrap();
+
+    test_reader_string("tests/data/TASKS.md", |mds| {
+        let mut bytes = Vec::new();
+        let toggle = mds.iter().cloned().map(|md| match md {
+            Md::ListTask(checked) => Md::ListTask(!checked),
+            md => md,
+        });
+
+        Encoder::new(toggle, &mut bytes).encode_md().unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), toggled);
+    });
+}
+
+#[test]
+fn definitions() {
+    test_reader_string("tests/data/DEFINITIONS.md", |mds| {
+        let expected = [
+            Md::DefinitionList,
+            Md::DefinitionTerm,
+            Md::Text("Apple".into()),
+            Md::DefinitionTerm,
+            Md::Text("Pomme".into()),
+            Md::ListItem,
+            Md::Text("Pomaceous fruit".into()),
+            Md::ListItem,
+            Md::Text("Red".into()),
+            Md::DefinitionTerm,
+            Md::Text("Orange".into()),
+            Md::ListItem,
+            Md::Text("Citrus fruit".into()),
+            Md::ListClose,
+            Md::Paragraph,
+            Md::Text("Just text".into()),
+            Md::DefinitionList,
+            Md::DefinitionTerm,
+            Md::Text("Banana".into()),
+            Md::ListItem,
+            Md::Paragraph,
+            Md::Text("Yellow".into()),
+            Md::Paragraph,
+            Md::Text("Second paragraph".into()),
+            Md::ListClose,
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let definitions =
+        fs::read_to_string("tests/data/ALL_DEFINITIONS.md").unwrap();
+
+    test_reader_string("tests/data/DEFINITIONS.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), definitions);
+    });
+}
+
+#[test]
+fn fenced_code() {
+    test_reader_string("tests/data/FENCED_CODE.md", |mds| {
+        let expected = [
+            Md::Paragraph,
+            Md::Text("Some code:".into()),
+            Md::SyntaxHighlighting("rust".into()),
+            Md::Codeblock("# not a heading\nfn main() {}\n".into()),
+            Md::Codeblock("> not a quote\n\n```\nstill code\n".into()),
+            Md::Codeblock("spaces after backticks\n".into()),
+            Md::Codeblock("spaces after tildes\n".into()),
+            Md::UnorderedList,
+            Md::ListItem,
+            Md::Text("Item".into()),
+            Md::SyntaxHighlighting("sh title=\"run\"".into()),
+            Md::Codeblock("cargo test\n".into()),
+            Md::ListItem,
+            Md::Text("Next".into()),
+            Md::ListClose,
+            Md::Codeblock("unclosed\n".into()),
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let fenced_code =
+        fs::read_to_string("tests/data/ALL_FENCED_CODE.md").unwrap();
+
+    test_reader_string("tests/data/FENCED_CODE.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), fenced_code);
+    });
+}
+
+#[test]
+fn indented_code() {
+    test_reader_string("tests/data/INDENTED_CODE.md", |mds| {
+        let expected = [
+            Md::Paragraph,
+            Md::Text("Text".into()),
+            Md::Text("not code".into()),
+            Md::Codeblock("# code\nline\n\n  more\n".into()),
+            Md::Paragraph,
+            Md::Text("After".into()),
+            Md::UnorderedList,
+            Md::ListItem,
+            Md::Paragraph,
+            Md::Text("Item".into()),
+            Md::Codeblock("item code\n".into()),
+            Md::ListItem,
+            Md::Paragraph,
+            Md::Text("Next".into()),
+            Md::ListClose,
+            Md::QuoteOpen,
+            Md::Codeblock("quoted\n\ncode\n".into()),
+            Md::QuoteClose,
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let indented_code =
+        fs::read_to_string("tests/data/ALL_INDENTED_CODE.md").unwrap();
+
+    test_reader_string("tests/data/INDENTED_CODE.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .indent_code(true)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), indented_code);
+    });
+}
+
+#[test]
+fn tables() {
+    test_reader_string("tests/data/TABLES.md", |mds| {
+        let expected = [
+            Md::TableLeft,
+            Md::TableCentered,
+            Md::TableRight,
+            Md::TableCell,
+            Md::Text("Method".into()),
+            Md::TableCell,
+            Md::Text("Path".into()),
+            Md::TableCell,
+            Md::Text("Status".into()),
+            Md::TableCell,
+            Md::Code("GET".into()),
+            Md::TableCell,
+            Md::Text("/items".into()),
+            Md::TableCell,
+            Md::Text("200".into()),
+            Md::TableCell,
+            Md::Text("POST".into()),
+            Md::TableCell,
+            Md::Text("/items | /new".into()),
+            Md::TableCell,
+            Md::Text("201".into()),
+            Md::TableCell,
+            Md::Text("DELETE".into()),
+            Md::TableCell,
+            Md::Text("/items/{id}".into()),
+            Md::TableCell,
+            Md::TableLeft,
+            Md::TableLeft,
+            Md::TableCell,
+            Md::Text("Name".into()),
+            Md::TableCell,
+            Md::Text("Description".into()),
+            Md::TableCell,
+            Md::Text("one".into()),
+            Md::TableCell,
+            Md::QuoteOpen,
+            Md::Paragraph,
+            Md::Text("Not a row".into()),
+            Md::QuoteClose,
+            Md::Paragraph,
+            Md::Text("| Just text |".into()),
+            Md::Text("Still a paragraph".into()),
+            Md::UnorderedList,
+            Md::ListItem,
+            Md::TableLeft,
+            Md::TableLeft,
+            Md::TableCell,
+            Md::Text("In".into()),
+            Md::TableCell,
+            Md::Text("List".into()),
+            Md::TableCell,
+            Md::Text("a".into()),
+            Md::TableCell,
+            Md::Text("b".into()),
+            Md::ListClose,
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let tables = fs::read_to_string("tests/data/ALL_TABLES.md").unwrap();
+
+    test_reader_string("tests/data/TABLES.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), tables);
+    });
+}
+
+#[test]
+fn alerts() {
+    test_reader_string("tests/data/ALERTS.md", |mds| {
+        let expected = [
+            Md::QuoteOpen,
+            Md::Admonition("WARNING".into()),
+            Md::Paragraph,
+            Md::Text("My warning text".into()),
+            Md::Paragraph,
+            Md::Text("Some more information about the warning".into()),
+            Md::QuoteClose,
+            Md::QuoteOpen,
+            Md::Admonition("NOTE".into()),
+            Md::Paragraph,
+            Md::Text("Separate line more information".into()),
+            Md::QuoteClose,
+            Md::QuoteOpen,
+            Md::Admonition("TIP".into()),
+            Md::Paragraph,
+            Md::Text("Double space more information".into()),
+            Md::QuoteClose,
+            Md::QuoteOpen,
+            Md::Admonition("important".into()),
+            Md::Text("Same line title".into()),
+            Md::Paragraph,
+            Md::Text("Body after the title".into()),
+            Md::QuoteClose,
+            Md::QuoteOpen,
+            Md::Admonition("NOTE".into()),
+            Md::Code("Code".into()),
+            Md::Text(" ".into()),
+            Md::Italic(true),
+            Md::Text("title".into()),
+            Md::Italic(false),
+            Md::Paragraph,
+            Md::Text("Body".into()),
+            Md::QuoteClose,
+            Md::QuoteOpen,
+            Md::Admonition("CAUTION".into()),
+            Md::Paragraph,
+            Md::Text("Lazy body".into()),
+            Md::QuoteClose,
+            Md::QuoteOpen,
+            Md::Admonition("WARNING".into()),
+            Md::Paragraph,
+            Md::Text("Lazy double space".into()),
+            Md::QuoteClose,
+            Md::QuoteOpen,
+            Md::Paragraph,
+            Md::Text("[!INFO]".into()),
+            Md::Text("Not an alert".into()),
+            Md::QuoteClose,
+            Md::QuoteOpen,
+            Md::Paragraph,
+            Md::Text("Text".into()),
+            Md::Text("[!NOTE]".into()),
+            Md::QuoteClose,
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let alerts = fs::read_to_string("tests/data/ALL_ALERTS.md").unwrap();
+
+    test_reader_string("tests/data/ALERTS.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), alerts);
+    });
+
+    test_reader_string("tests/data/ALL_ALERTS.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), alerts);
+    });
+}
+
+#[test]
+fn admonitions() {
+    test_reader_string("tests/data/ADMONITIONS.md", |mds| {
+        let expected = [
+            Md::QuoteOpen,
+            Md::Admonition("warning".into()),
+            Md::Text("My warning text".into()),
+            Md::Paragraph,
+            Md::Text("Some more information about the warning".into()),
+            Md::UnorderedList,
+            Md::ListItem,
+            Md::Text("With a list".into()),
+            Md::ListClose,
+            Md::QuoteClose,
+            Md::QuoteOpen,
+            Md::Admonition("bug".into()),
+            Md::Paragraph,
+            Md::Text("Body right after".into()),
+            Md::QuoteClose,
+            Md::QuoteOpen,
+            Md::Admonition("note".into()),
+            Md::Paragraph,
+            Md::Text("No title".into()),
+            Md::Text("Lazy continuation".into()),
+            Md::QuoteClose,
+            Md::QuoteOpen,
+            Md::Admonition("example".into()),
+            Md::Text("A ".into()),
+            Md::Italic(true),
+            Md::Text("formatted".into()),
+            Md::Italic(false),
+            Md::Text(" ".into()),
+            Md::Code("title".into()),
+            Md::Paragraph,
+            Md::Text("Body".into()),
+            Md::QuoteClose,
+            Md::Paragraph,
+            Md::Text("Paragraph".into()),
+            Md::Text("!!! tip \"Doesn't interrupt\"".into()),
+            Md::QuoteOpen,
+            Md::Admonition("tip".into()),
+            Md::Text("After a blank line".into()),
+            Md::Codeblock("Indented code\n".into()),
+            Md::QuoteClose,
+            Md::Paragraph,
+            Md::Text("Not in the admonition".into()),
+            Md::Paragraph,
+            Md::Text("!!! info \"Title\" extra".into()),
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let admonitions =
+        fs::read_to_string("tests/data/ALL_ADMONITIONS.md").unwrap();
+
+    test_reader_string("tests/data/ADMONITIONS.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .mkdocs(true)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), admonitions);
+    });
+
+    // Formatted titles are written within the quotes
+    test_reader_string("tests/data/ALL_ADMONITIONS.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .mkdocs(true)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), admonitions);
+    });
+}
+
+#[test]
+fn details() {
+    test_reader_string("tests/data/DETAILS.md", |mds| {
+        let expected = [
+            Md::QuoteOpen,
+            Md::Details("My summary".into(), false),
+            Md::Text("Continued summary".into()),
+            Md::Paragraph,
+            Md::Text("Details that can be expanded".into()),
+            Md::QuoteClose,
+            Md::QuoteOpen,
+            Md::Details("My summary".into(), true),
+            Md::Paragraph,
+            Md::Text("Details that can be collapsed".into()),
+            Md::QuoteClose,
+            Md::QuoteOpen,
+            Md::Admonition("info".into()),
+            Md::Details("My summary".into(), false),
+            Md::Paragraph,
+            Md::Text("Details that can be expanded".into()),
+            Md::QuoteClose,
+            Md::QuoteOpen,
+            Md::Admonition("warning".into()),
+            Md::Details("Warning summary".into(), false),
+            Md::Paragraph,
+            Md::Text("Details with a kind".into()),
+            Md::QuoteClose,
+            Md::QuoteOpen,
+            Md::Admonition("note".into()),
+            Md::Details("note".into(), true),
+            Md::UnorderedList,
+            Md::ListItem,
+            Md::Text("Details that can be collapsed".into()),
+            Md::ListClose,
+            Md::QuoteClose,
+            Md::Paragraph,
+            Md::Text("Not details".into()),
+            Md::Text("++ not a summary".into()),
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let details = fs::read_to_string("tests/data/ALL_DETAILS.md").unwrap();
+
+    test_reader_string("tests/data/DETAILS.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), details);
+
+        // Details keep their syntax with MkDocs admonitions
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .mkdocs(true)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), details);
+    });
+
+    // Details without a block quote only have the summary
+    let mut bytes = Vec::new();
+
+    Encoder::new(
+        [
+            Md::Details("Stray summary".into(), true),
+            Md::Text("continued".into()),
+            Md::Paragraph,
+            Md::Text("Not in the details".into()),
+        ],
+        &mut bytes,
+    )
+    .encode_md()
+    .unwrap();
+    assert_eq!(
+        str::from_utf8(&bytes).unwrap(),
+        "+++ Stray summary\n    continued\n\nNot in the details\n",
+    );
+}
+
+#[test]
+fn footnotes() {
+    let string = fs::read_to_string("tests/data/FOOTNOTES.md").unwrap();
+    let mut warnings = Vec::new();
+    let mds = Decoder::from_str(&string)
+        .filter_map(|md| match md {
+            Ok(md) => Some(md),
+            Err(Error::Warning(warning)) => {
+                warnings.push(warning);
+                None
+            }
+            Err(error) => panic!("{error:?}"),
+        })
+        .collect::<Vec<_>>();
+    let expected = [
+        Md::Paragraph,
+        Md::Text("Some text".into()),
+        Md::FootnoteRef("1".into()),
+        Md::Text(" with a note".into()),
+        Md::FootnoteRef("Long".into()),
+        Md::Text(".".into()),
+        Md::Text("A second line with".into()),
+        Md::FootnoteRef("1".into()),
+        Md::Text("".into()),
+        Md::Text("a reference at the end.".into()),
+        Md::FootnoteOpen("long".into()),
+        Md::Paragraph,
+        Md::Text("The long note.".into()),
+        Md::Paragraph,
+        Md::Text("With a second paragraph.".into()),
+        Md::FootnoteClose,
+        Md::Paragraph,
+        Md::Text("Not".into()),
+        Md::FootnoteRef("missing".into()),
+        Md::Text(" defined, and [^not a label].".into()),
+        Md::FootnoteOpen("1".into()),
+        Md::Paragraph,
+        Md::Text("The first note.".into()),
+        Md::FootnoteClose,
+    ];
+
+    assert_eq!(mds, expected);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].kind(), &WarningKind::UndefinedFootnote);
+    assert_eq!(warnings[0].line_number(), 9);
+    assert_eq!(
+        warnings[0].line_text(),
+        "Not[^missing] defined, and [^not a label].",
+    );
+
+    let footnotes = fs::read_to_string("tests/data/ALL_FOOTNOTES.md").unwrap();
+    let mut bytes = Vec::new();
+
+    Encoder::new(mds, &mut bytes).encode_md().unwrap();
+    assert_eq!(str::from_utf8(&bytes).unwrap(), footnotes);
+}
+
+#[test]
+fn links() {
+    test_reader_string("tests/data/LINKS.md", |mds| {
+        let expected = [
+            Md::LinkKey("Example".into()),
+            Md::LinkVal("https://example.org".into()),
+            Md::LinkKey("1".into()),
+            Md::LinkVal("https://example.org/a page".into()),
+            Md::Title("My Title".into()),
+            Md::LinkKey("Single".into()),
+            Md::LinkVal("https://example.org/single".into()),
+            Md::Title("Single quoted".into()),
+            Md::LinkKey("Paren".into()),
+            Md::LinkVal("https://example.org/paren".into()),
+            Md::Title("Parenthesized".into()),
+            Md::LinkKey("Multi line".into()),
+            Md::LinkVal("https://example.org/multi".into()),
+            Md::Title("Title spanning\ntwo lines".into()),
+            Md::Paragraph,
+            Md::Text("See ".into()),
+            Md::LinkRef("Example".into()),
+            Md::Text(", ".into()),
+            Md::LinkNum("the docs".into(), 1),
+            Md::Text(" and ".into()),
+            Md::LinkRef("single".into()),
+            Md::Text(" or ".into()),
+            Md::LinkRef("paren".into()),
+            Md::Text(".".into()),
+            Md::Paragraph,
+            Md::Text("Inline ".into()),
+            Md::LinkRef("link".into()),
+            Md::LinkVal("https://example.org/inline".into()),
+            Md::Title("Inline title".into()),
+            Md::Text(" and".into()),
+            Md::Text("".into()),
+            Md::ImageRef("An image".into()),
+            Md::LinkVal("image.png".into()),
+            Md::Text(" with ".into()),
+            Md::ImageNum("Numbered image".into(), 1),
+            Md::Text(".".into()),
+            Md::Paragraph,
+            Md::Text("Full ".into()),
+            Md::LinkRef("reference".into()),
+            Md::LinkLabel("Single".into()),
+            Md::Text(" and ".into()),
+            Md::ImageRef("image".into()),
+            Md::LinkLabel("PAREN".into()),
+            Md::Text(" links.".into()),
+            Md::Paragraph,
+            Md::LinkRef("Undefined".into()),
+            Md::Text(", ".into()),
+            Md::LinkNum("undefined".into(), 2),
+            Md::Text(" and ".into()),
+            Md::LinkRef("text".into()),
+            Md::LinkLabel("undefined".into()),
+            Md::Text(" stay as text, like \\[escaped].".into()),
+            Md::Paragraph,
+            Md::Text("A paragraph".into()),
+            Md::Text("".into()),
+            Md::LinkRef("Not".into()),
+            Md::Text(": a definition".into()),
+            Md::QuoteOpen,
+            Md::LinkKey("Quoted".into()),
+            Md::LinkVal("https://example.org/quoted".into()),
+            Md::Title("Quoted title\non two lines".into()),
+            Md::QuoteClose,
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let links = fs::read_to_string("tests/data/ALL_LINKS.md").unwrap();
+
+    test_reader_string("tests/data/LINKS.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), links);
+    });
+}
+
+#[test]
+fn comments() {
+    test_reader_string("tests/data/COMMENTS.md", |mds| {
+        assert_eq!(
+            mds[..2],
+            [
+                Md::Comment("Editor note: check the numbers".into()),
+                Md::Paragraph,
+            ],
+        );
+        // HTML comments are text unless enabled
+        assert_eq!(
+            mds[6..8],
+            [Md::Paragraph, Md::Text("<!-- HTML comment -->".into())]
+        );
+    });
+
+    let md = fs::read_to_string("tests/data/COMMENTS.md").unwrap();
+    let mds = Decoder::from_str(&md)
+        .html_comments(true)
+        .collect::<Result<'_, Vec<_>>>()
+        .unwrap();
+    let expected = [
+        Md::Comment("Editor note: check the numbers".into()),
+        Md::Paragraph,
+        Md::Text("Paragraph text".into()),
+        Md::Text("".into()),
+        Md::LinkRef("Not a comment".into()),
+        Md::Text(": #".into()),
+        Md::Comment("HTML comment".into()),
+        Md::Comment("Multi line\nHTML comment".into()),
+        Md::Paragraph,
+        Md::Text("Text after the comment".into()),
+        Md::QuoteOpen,
+        Md::Comment("Quoted note".into()),
+        Md::Comment("Quoted\nHTML comment".into()),
+        Md::Paragraph,
+        Md::Text("with text".into()),
+        Md::QuoteClose,
+        Md::LinkKey("Link".into()),
+        Md::LinkVal("https://example.org".into()),
+        Md::Comment("Another note".into()),
+    ];
+
+    assert_eq!(mds, expected);
+
+    let comments = fs::read_to_string("tests/data/ALL_COMMENTS.md").unwrap();
+    let mut bytes = Vec::new();
+
+    Encoder::new(mds, &mut bytes).encode_md().unwrap();
+    assert_eq!(str::from_utf8(&bytes).unwrap(), comments);
+}
+
+#[test]
+fn heading_ids() {
+    test_reader_string("tests/data/HEADING_IDS.md", |mds| {
+        let expected = [
+            Md::Heading1,
+            Md::HeadingId("custom-id".into()),
+            Md::Text("Heading".into()),
+            Md::Heading2,
+            Md::HeadingId("second".into()),
+            Md::Text("Second heading".into()),
+            Md::Heading3,
+            Md::Text("No ID {not an id}".into()),
+            Md::Heading1,
+            Md::HeadingId("setext".into()),
+            Md::Text("Setext heading".into()),
+            Md::Text("spanning lines".into()),
+            Md::Paragraph,
+            Md::Text("Not a {#heading} id".into()),
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let heading_ids =
+        fs::read_to_string("tests/data/ALL_HEADING_IDS.md").unwrap();
+
+    test_reader_string("tests/data/HEADING_IDS.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), heading_ids);
+
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds[..6].iter().cloned(), &mut bytes)
+            .setext(true)
+            .encode_md()
+            .unwrap();
+        assert_eq!(
+            str::from_utf8(&bytes).unwrap(),
+            "Heading {#custom-id}\n====================\n\n\
+             Second heading {#second}\n------------------------\n",
+        );
+    });
+}
+
+#[test]
+fn captions() {
+    test_reader_string("tests/data/CAPTIONS.md", |mds| {
+        let expected = [
+            Md::Paragraph,
+            Md::ImageRef("Settings page".into()),
+            Md::LinkVal("settings.png".into()),
+            Md::Caption,
+            Md::Text("The settings page, with the theme selected.".into()),
+            Md::Paragraph,
+            Md::ImageRef("Dashboard".into()),
+            Md::LinkVal("dashboard.png".into()),
+            Md::Title("Dashboard".into()),
+            Md::Caption,
+            Md::Text("Same line caption".into()),
+            Md::Paragraph,
+            Md::ImageNum("Numbered".into(), 1),
+            Md::Caption,
+            Md::Text("Caption with a ".into()),
+            Md::LinkRef("link".into()),
+            Md::LinkVal("https://example.org".into()),
+            Md::Paragraph,
+            Md::ImageRef("No caption".into()),
+            Md::LinkVal("alone.png".into()),
+            Md::Paragraph,
+            Md::Text("Text before ".into()),
+            Md::ImageRef("an image".into()),
+            Md::LinkVal("inline.png".into()),
+            Md::QuoteOpen,
+            Md::Paragraph,
+            Md::ImageRef("Quoted".into()),
+            Md::LinkVal("quoted.png".into()),
+            Md::Caption,
+            Md::Text("Quoted caption".into()),
+            Md::QuoteClose,
+            Md::LinkKey("1".into()),
+            Md::LinkVal("numbered.png".into()),
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let captions = fs::read_to_string("tests/data/ALL_CAPTIONS.md").unwrap();
+
+    test_reader_string("tests/data/CAPTIONS.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), captions);
+    });
+}
+
+#[test]
+fn front_matter() {
+    test_reader_string("tests/data/FRONT_MATTER.md", |mds| {
+        let expected = [
+            Md::FrontMatter(
+                "---\ntitle: Front matter\ntags: [a, b]\n---\n".into(),
+            ),
+            Md::Heading1,
+            Md::Text("Heading".into()),
+            Md::Paragraph,
+            Md::Text("Text".into()),
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let front_matter =
+        fs::read_to_string("tests/data/ALL_FRONT_MATTER.md").unwrap();
+
+    test_reader_string("tests/data/FRONT_MATTER.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), front_matter);
+    });
+
+    let toml = Decoder::from_str("+++\ntitle = \"TOML\"\n+++\n\nText\n")
+        .collect::<Result<'_, Vec<_>>>()
+        .unwrap();
+
+    assert_eq!(
+        toml,
+        [
+            Md::FrontMatter("+++\ntitle = \"TOML\"\n+++\n".into()),
+            Md::Paragraph,
+            Md::Text("Text".into()),
+        ],
+    );
+
+    // Only at the very beginning, and closed
+    let not_front_matter = Decoder::from_str("Text\n\n---\ntitle: late\n---\n")
+        .collect::<Result<'_, Vec<_>>>()
+        .unwrap();
+    let not_closed = Decoder::from_str("---\nNot closed\n")
+        .collect::<Result<'_, Vec<_>>>()
+        .unwrap();
+
+    assert_eq!(
+        not_front_matter,
+        [
+            Md::Paragraph,
+            Md::Text("Text".into()),
+            Md::HorizontalRule,
+            Md::Heading2,
+            Md::Text("title: late".into()),
+        ],
+    );
+    assert_eq!(
+        not_closed,
+        [
+            Md::HorizontalRule,
+            Md::Paragraph,
+            Md::Text("Not closed".into()),
+        ],
+    );
+
+    // Not followed by a blank line
+    let rules = Decoder::from_str("---\n\nIntro\n\n---\n\nBody\n")
+        .collect::<Result<'_, Vec<_>>>()
+        .unwrap();
+
+    assert_eq!(
+        rules,
+        [
+            Md::HorizontalRule,
+            Md::Paragraph,
+            Md::Text("Intro".into()),
+            Md::HorizontalRule,
+            Md::Paragraph,
+            Md::Text("Body".into()),
+        ],
+    );
+
+    // A thematic break opening the document isn't written as front matter
+    let mut bytes = Vec::new();
+
+    Encoder::new(rules.iter().cloned(), &mut bytes)
+        .encode_md()
+        .unwrap();
+
+    let md = str::from_utf8(&bytes).unwrap();
+    let decoded = Decoder::from_str(md)
+        .collect::<Result<'_, Vec<_>>>()
+        .unwrap();
+
+    assert_eq!(md, "***\n\nIntro\n\n---\n\nBody\n");
+    assert_eq!(decoded, rules);
+}
+
+#[test]
+fn ambiguous_headings() {
+    let string =
+        fs::read_to_string("tests/data/AMBIGUOUS_HEADINGS.md").unwrap();
+
+    for strict in [false, true] {
+        let mut warnings = Vec::new();
+        let mds = Decoder::from_str(&string)
+            .strict(strict)
+            .filter_map(|md| match md {
+                Ok(md) => Some(md),
+                Err(Error::Warning(warning)) => {
+                    warnings.push(warning);
+                    None
+                }
+                Err(error) => panic!("{error:?}"),
+            })
+            .collect::<Vec<_>>();
+        let expected = if strict {
+            vec![
+                Md::Paragraph,
+                Md::Text("#NoSpace heading".into()),
+                Md::Paragraph,
+                Md::Text("####### Seven".into()),
+                Md::Heading1,
+                Md::Text("Fine".into()),
+                Md::Paragraph,
+                Md::Text("Text".into()),
+                Md::Text("#tag continues".into()),
+                Md::Heading1,
+                Md::Text("".into()),
+            ]
+        } else {
+            vec![
+                Md::Heading1,
+                Md::Text("NoSpace heading".into()),
+                Md::Paragraph,
+                Md::Text("####### Seven".into()),
+                Md::Heading1,
+                Md::Text("Fine".into()),
+                Md::Paragraph,
+                Md::Text("Text".into()),
+                Md::Heading1,
+                Md::Text("tag continues".into()),
+                Md::Heading1,
+                Md::Text("".into()),
+            ]
+        };
+
+        assert_eq!(mds, expected);
+        assert_eq!(warnings.len(), 3);
+        for (warning, (line_number, line_text)) in warnings.iter().zip([
+            (1, "#NoSpace heading"),
+            (3, "####### Seven"),
+            (8, "#tag continues"),
+        ]) {
+            assert_eq!(warning.kind(), &WarningKind::AmbiguousHeading);
+            assert_eq!(warning.line_number(), line_number);
+            assert_eq!(warning.line_text(), line_text);
+        }
+    }
+}
+
+#[test]
+fn line_breaks() {
+    test_reader_string("tests/data/LINE_BREAKS.md", |mds| {
+        let expected = [
+            Md::Paragraph,
+            Md::Text("Two spaces".into()),
+            Md::LineBreak,
+            Md::Text("backslash".into()),
+            Md::LineBreak,
+            Md::Text("br".into()),
+            Md::LineBreak,
+            Md::Text("inline and".into()),
+            Md::LineBreak,
+            Md::Text("escaped \\\\\\\\".into()),
+            Md::Text("last line".into()),
+            Md::QuoteOpen,
+            Md::Paragraph,
+            Md::Text("Quoted".into()),
+            Md::LineBreak,
+            Md::Text("break".into()),
+            Md::QuoteClose,
+            Md::TableLeft,
+            Md::TableLeft,
+            Md::TableCell,
+            Md::Text("A".into()),
+            Md::LineBreak,
+            Md::Text("B".into()),
+            Md::TableCell,
+            Md::Text("C".into()),
+            Md::TableCell,
+            Md::Text("1".into()),
+            Md::LineBreak,
+            Md::Text("2".into()),
+            Md::TableCell,
+            Md::Text("3".into()),
+            Md::Heading1,
+            Md::Text("Heading".into()),
+            Md::LineBreak,
+            Md::Text("break".into()),
+            Md::Paragraph,
+            Md::Text("Not a break\\".into()),
+            Md::Paragraph,
+            Md::Text("Ends in a break".into()),
+            Md::LineBreak,
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let line_breaks =
+        fs::read_to_string("tests/data/ALL_LINE_BREAKS.md").unwrap();
+
+    test_reader_string("tests/data/LINE_BREAKS.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), line_breaks);
+
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds[..17].iter().cloned(), &mut bytes)
+            .space_breaks(true)
+            .encode_md()
+            .unwrap();
+        assert_eq!(
+            str::from_utf8(&bytes).unwrap(),
+            "Two spaces  \nbackslash  \nbr  \ninline and  \n\
+             escaped \\\\\\\\ last line\n\n> Quoted  \n> break\n",
+        );
+    });
+}
+
+#[test]
+fn emphasis() {
+    test_reader_string("tests/data/EMPHASIS.md", |mds| {
+        let expected = [
+            Md::Heading1,
+            Md::Text("Emphasis".into()),
+            Md::Paragraph,
+            Md::Text("Text with ".into()),
+            Md::Italic(true),
+            Md::Text("italic".into()),
+            Md::Italic(false),
+            Md::Text(", ".into()),
+            Md::Bold(true),
+            Md::Text("bold".into()),
+            Md::Bold(false),
+            Md::Text(" and ".into()),
+            Md::BoldItalic(true),
+            Md::Text("bold italic".into()),
+            Md::BoldItalic(false),
+            Md::Text(" words, or ".into()),
+            Md::Italic(true),
+            Md::Text("italic".into()),
+            Md::Italic(false),
+            Md::Text(",".into()),
+            Md::Text("".into()),
+            Md::Bold(true),
+            Md::Text("bold".into()),
+            Md::Bold(false),
+            Md::Text(" and ".into()),
+            Md::BoldItalic(true),
+            Md::Text("bold italic".into()),
+            Md::BoldItalic(false),
+            Md::Text(" with underscores.".into()),
+            Md::Paragraph,
+            Md::Text("Emphasis can ".into()),
+            Md::Italic(true),
+            Md::Text("span".into()),
+            Md::Text("lines".into()),
+            Md::Italic(false),
+            Md::Text(" and nest: ".into()),
+            Md::Italic(true),
+            Md::Text("italic with ".into()),
+            Md::Bold(true),
+            Md::Text("bold".into()),
+            Md::Bold(false),
+            Md::Text(" inside".into()),
+            Md::Italic(false),
+            Md::Text(", ".into()),
+            Md::Bold(true),
+            Md::Text("bold with ".into()),
+            Md::Italic(true),
+            Md::Text("italic".into()),
+            Md::Italic(false),
+            Md::Text("".into()),
+            Md::Text("inside".into()),
+            Md::Bold(false),
+            Md::Text(", ".into()),
+            Md::Italic(true),
+            Md::Bold(true),
+            Md::Text("italic".into()),
+            Md::Bold(false),
+            Md::Text(" then bold".into()),
+            Md::Italic(false),
+            Md::Text(" and ".into()),
+            Md::Bold(true),
+            Md::Italic(true),
+            Md::Text("bold".into()),
+            Md::Italic(false),
+            Md::Text(" then italic".into()),
+            Md::Bold(false),
+            Md::Text(".".into()),
+            Md::Paragraph,
+            Md::Text("Intraword stars em".into()),
+            Md::Italic(true),
+            Md::Text("pha".into()),
+            Md::Italic(false),
+            Md::Text("size, but snake_case_words and 2_000_000 stay as".into()),
+            Md::Text("they are.".into()),
+            Md::Paragraph,
+            Md::Text("Unmatched delimiters are text: * not italic *, a lone * star and".into()),
+            Md::Text("**unclosed bold.".into()),
+            Md::Paragraph,
+            Md::Text("The rule of three keeps ".into()),
+            Md::Italic(true),
+            Md::Text("foo**bar".into()),
+            Md::Italic(false),
+            Md::Text(" italic.".into()),
+            Md::Paragraph,
+            Md::Text("Escaped \\*stars\\* and \\_underscores\\_ aren't emphasis.".into()),
+            Md::UnorderedList,
+            Md::ListItem,
+            Md::Italic(true),
+            Md::Text("Italic".into()),
+            Md::Italic(false),
+            Md::Text(" item".into()),
+            Md::ListItem,
+            Md::Bold(true),
+            Md::Text("Bold".into()),
+            Md::Bold(false),
+            Md::Text(" item".into()),
+            Md::ListClose,
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let emphasis = fs::read_to_string("tests/data/ALL_EMPHASIS.md").unwrap();
+
+    test_reader_string("tests/data/EMPHASIS.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), emphasis);
+    });
+}
+
+#[test]
+fn styles() {
+    test_reader_string("tests/data/STYLES.md", |mds| {
+        let expected = [
+            Md::Heading1,
+            Md::Text("Styles".into()),
+            Md::Paragraph,
+            Md::Text("Text with ".into()),
+            Md::Strikethrough(true),
+            Md::Text("strikethrough".into()),
+            Md::Strikethrough(false),
+            Md::Text(", ".into()),
+            Md::Highlight(true),
+            Md::Text("highlight".into()),
+            Md::Highlight(false),
+            Md::Text(", H".into()),
+            Md::Subscript(true),
+            Md::Text("2".into()),
+            Md::Subscript(false),
+            Md::Text("O subscript, x".into()),
+            Md::Superscript(true),
+            Md::Text("2".into()),
+            Md::Superscript(false),
+            Md::Text("".into()),
+            Md::Text("superscript and ".into()),
+            Md::Underline(true),
+            Md::Text("underline".into()),
+            Md::Underline(false),
+            Md::Text(" styles.".into()),
+            Md::Paragraph,
+            Md::Text("Underline also comes from ".into()),
+            Md::Underline(true),
+            Md::Text("ins".into()),
+            Md::Underline(false),
+            Md::Text(" and ".into()),
+            Md::Underline(true),
+            Md::Text("u".into()),
+            Md::Underline(false),
+            Md::Text(" tags.".into()),
+            Md::Paragraph,
+            Md::Text("Styles nest: ".into()),
+            Md::Strikethrough(true),
+            Md::Text("struck with ".into()),
+            Md::Highlight(true),
+            Md::Text("highlight".into()),
+            Md::Highlight(false),
+            Md::Text(" and ".into()),
+            Md::Italic(true),
+            Md::Text("italic".into()),
+            Md::Italic(false),
+            Md::Strikethrough(false),
+            Md::Text(".".into()),
+            Md::Paragraph,
+            Md::Text("Tildes differ: ".into()),
+            Md::Strikethrough(true),
+            Md::Text("one~ and ~two".into()),
+            Md::Strikethrough(false),
+            Md::Text(" pair the doubles, ~~~three~~~ isn't a".into()),
+            Md::Text("style and ~approximately 5 stays text.".into()),
+            Md::Paragraph,
+            Md::Text("Dashes in well--known--words, a -- b and 2--3 aren't underlined.".into()),
+            Md::Paragraph,
+            Md::Text("Escaped \\~\\~tildes\\~\\~ and \\=\\=equals\\=\\= aren't styles.".into()),
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let styles = fs::read_to_string("tests/data/ALL_STYLES.md").unwrap();
+
+    test_reader_string("tests/data/STYLES.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), styles);
+    });
+}
+
+#[test]
+fn code_spans() {
+    test_reader_string("tests/data/CODE_SPANS.md", |mds| {
+        let expected = [
+            Md::Heading1,
+            Md::Text("Code ".into()),
+            Md::Code("spans".into()),
+            Md::Paragraph,
+            Md::Text("Inline ".into()),
+            Md::Code("code".into()),
+            Md::Text(", escaped ".into()),
+            Md::Code("<b>&</b>".into()),
+            Md::Text(", ".into()),
+            Md::Code("code with ` backtick".into()),
+            Md::Text(" and ".into()),
+            Md::Code("code with `` two".into()),
+            Md::Text(".".into()),
+            Md::Paragraph,
+            Md::Text("One space is stripped from ".into()),
+            Md::Code("both".into()),
+            Md::Text(" ends, but not from ".into()),
+            Md::Code(" one".into()),
+            Md::Text(" end or".into()),
+            Md::Text("".into()),
+            Md::Code("  ".into()),
+            Md::Text(" only spaces.".into()),
+            Md::Paragraph,
+            Md::Text("Backslashes are literal in ".into()),
+            Md::Code("code\\".into()),
+            Md::Text(", and \\`escaped` backticks aren't".into()),
+            Md::Text("code.".into()),
+            Md::Paragraph,
+            Md::Text("Code spans take precedence: ".into()),
+            Md::Code("*not emphasis*".into()),
+            Md::Text(", ".into()),
+            Md::Italic(true),
+            Md::Code("code".into()),
+            Md::Text(" emphasis".into()),
+            Md::Italic(false),
+            Md::Text(" and".into()),
+            Md::Text("[not a ".into()),
+            Md::Code("link](/url".into()),
+            Md::Text(").".into()),
+            Md::Paragraph,
+            Md::Text("Code spans continue ".into()),
+            Md::Code("across lines".into()),
+            Md::Text(" like text.".into()),
+            Md::Paragraph,
+            Md::Text("Unmatched ``backticks stay text.".into()),
+            Md::TableLeft,
+            Md::TableLeft,
+            Md::TableCell,
+            Md::Text("Cell".into()),
+            Md::TableCell,
+            Md::Code("a | b".into()),
+            Md::TableCell,
+            Md::Italic(true),
+            Md::Text("Emphasis".into()),
+            Md::Italic(false),
+            Md::TableCell,
+            Md::LinkRef("link".into()),
+            Md::LinkVal("/url".into()),
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let code_spans =
+        fs::read_to_string("tests/data/ALL_CODE_SPANS.md").unwrap();
+
+    test_reader_string("tests/data/CODE_SPANS.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), code_spans);
+    });
+}
+
+#[test]
+fn autolinks() {
+    test_reader_string("tests/data/AUTOLINKS.md", |mds| {
+        let expected = [
+            Md::Heading1,
+            Md::Text("Autolinks".into()),
+            Md::Paragraph,
+            Md::Text("Angle brackets link ".into()),
+            Md::Link("https://example.org".into()),
+            Md::Text(", ".into()),
+            Md::Link("mailto:me@example.org".into()),
+            Md::Text(" and".into()),
+            Md::Text("".into()),
+            Md::Link("me@example.org".into()),
+            Md::Text(", but not <not a link> or <p>.".into()),
+            Md::Paragraph,
+            Md::Text("Bare links ".into()),
+            Md::Link("https://example.org/path?q=1".into()),
+            Md::Text(", ".into()),
+            Md::Link("www.example.org".into()),
+            Md::Text(" and".into()),
+            Md::Text("".into()),
+            Md::Link("me+you@example.org".into()),
+            Md::Text(" work too.".into()),
+            Md::Paragraph,
+            Md::Text("Trailing punctuation isn't linked: ".into()),
+            Md::Link("www.example.org".into()),
+            Md::Text(". and".into()),
+            Md::Text("(".into()),
+            Md::Link("https://example.org/wiki/Foo_(bar)".into()),
+            Md::Text(") keeps balanced parentheses.".into()),
+            Md::Paragraph,
+            Md::Text("Entity references end links: ".into()),
+            Md::Link("https://example.org/a".into()),
+            Md::Text("&amp; and ".into()),
+            Md::Italic(true),
+            Md::Text("emphasis".into()),
+            Md::Text("around ".into()),
+            Md::Link("www.example.org".into()),
+            Md::Italic(false),
+            Md::Text(" too.".into()),
+            Md::Paragraph,
+            Md::Text("Not links: wwwexample.org, http://localhost, www.example_org.com_x and".into()),
+            Md::Text("user@localhost.".into()),
+            Md::Paragraph,
+            Md::Code("https://example.org".into()),
+            Md::Text(" in code and ".into()),
+            Md::LinkRef("www.example.org".into()),
+            Md::LinkVal("/url".into()),
+            Md::Text(" text aren't".into()),
+            Md::Text("autolinks.".into()),
+        ];
+
+        assert_eq!(mds, expected);
+    });
+
+    let autolinks = fs::read_to_string("tests/data/ALL_AUTOLINKS.md").unwrap();
+
+    test_reader_string("tests/data/AUTOLINKS.md", |mds| {
+        let mut bytes = Vec::new();
+
+        Encoder::new(mds.iter().cloned(), &mut bytes)
+            .encode_md()
+            .unwrap();
+        assert_eq!(str::from_utf8(&bytes).unwrap(), autolinks);
+    });
+}
@@ -1,6 +1,6 @@
 use std::fs;
 
-use marksad::{html::HtmlEncoder, Md};
+use marksad::{decode::Result, html::HtmlEncoder, Decoder, Md};
 
 #[test]
 fn markdown_to_html() {
@@ -43,3 +43,240 @@ fn markdown_to_html() {
 
     assert_eq!(string, expected);
 }
+
+fn file_to_html(path: &str) -> String {
+    let md = fs::read_to_string(path).unwrap();
+    let md = Decoder::from_str(&md)
+        .collect::<Result<'_, Vec<_>>>()
+        .unwrap();
+    let mut string = Vec::new();
+
+    HtmlEncoder::new(md, &mut string).encode_html().unwrap();
+    string.push(b'\n');
+
+    String::from_utf8(string).unwrap()
+}
+
+#[test]
+fn thematic_breaks_to_html() {
+    let expected =
+        fs::read_to_string("tests/data/THEMATIC_BREAKS.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/THEMATIC_BREAKS.md"), expected);
+}
+
+#[test]
+fn quotes_to_html() {
+    let expected = fs::read_to_string("tests/data/QUOTES.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/QUOTES.md"), expected);
+}
+
+#[test]
+fn lists_to_html() {
+    let expected = fs::read_to_string("tests/data/LISTS.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/LISTS.md"), expected);
+}
+
+#[test]
+fn ordered_lists_to_html() {
+    let expected = fs::read_to_string("tests/data/ORDERED_LISTS.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/ORDERED_LISTS.md"), expected);
+}
+
+#[test]
+fn tasks_to_html() {
+    let md = [
+        Md::UnorderedList,
+        Md::ListItem,
+        Md::ListTask(false),
+        Md::Text("Todo".into()),
+        Md::ListItem,
+        Md::ListTask(true),
+        Md::Text("Done".into()),
+        Md::ListClose,
+    ];
+    let mut disabled = Vec::new();
+    let mut enabled = Vec::new();
+
+    HtmlEncoder::new(md.clone(), &mut disabled)
+        .encode_html()
+        .unwrap();
+    HtmlEncoder::new(md, &mut enabled)
+        .enable_tasks(true)
+        .encode_html()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(disabled).unwrap(),
+        "<ul><li><input type=\"checkbox\" disabled> Todo</li>\
+         <li><input type=\"checkbox\" checked disabled> Done</li></ul>",
+    );
+    assert_eq!(
+        String::from_utf8(enabled).unwrap(),
+        "<ul><li><input type=\"checkbox\"> Todo</li>\
+         <li><input type=\"checkbox\" checked> Done</li></ul>",
+    );
+}
+
+#[test]
+fn definitions_to_html() {
+    let expected = fs::read_to_string("tests/data/DEFINITIONS.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/DEFINITIONS.md"), expected);
+}
+
+#[test]
+fn fenced_code_to_html() {
+    let expected = fs::read_to_string("tests/data/FENCED_CODE.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/FENCED_CODE.md"), expected);
+}
+
+#[test]
+fn indented_code_to_html() {
+    let expected = fs::read_to_string("tests/data/INDENTED_CODE.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/INDENTED_CODE.md"), expected);
+}
+
+#[test]
+fn tables_to_html() {
+    let expected = fs::read_to_string("tests/data/TABLES.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/TABLES.md"), expected);
+}
+
+#[test]
+fn alerts_to_html() {
+    let expected = fs::read_to_string("tests/data/ALERTS.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/ALERTS.md"), expected);
+}
+
+#[test]
+fn admonitions_to_html() {
+    let expected = fs::read_to_string("tests/data/ADMONITIONS.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/ADMONITIONS.md"), expected);
+}
+
+#[test]
+fn details_to_html() {
+    let expected = fs::read_to_string("tests/data/DETAILS.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/DETAILS.md"), expected);
+}
+
+#[test]
+fn footnotes_to_html() {
+    let md = fs::read_to_string("tests/data/FOOTNOTES.md").unwrap();
+    // Undefined footnotes are warnings, and written as text
+    let md = Decoder::from_str(&md).filter_map(|md| md.ok());
+    let expected = fs::read_to_string("tests/data/FOOTNOTES.html").unwrap();
+    let mut string = Vec::new();
+
+    HtmlEncoder::new(md, &mut string).encode_html().unwrap();
+    string.push(b'\n');
+    assert_eq!(String::from_utf8(string).unwrap(), expected);
+}
+
+#[test]
+fn nested_footnotes_to_html() {
+    let expected =
+        fs::read_to_string("tests/data/NESTED_FOOTNOTES.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/NESTED_FOOTNOTES.md"), expected);
+}
+
+#[test]
+fn links_to_html() {
+    let expected = fs::read_to_string("tests/data/LINKS.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/LINKS.md"), expected);
+}
+
+#[test]
+fn comments_to_html() {
+    let md = fs::read_to_string("tests/data/COMMENTS.md").unwrap();
+    let mds = Decoder::from_str(&md)
+        .html_comments(true)
+        .collect::<Result<'_, Vec<_>>>()
+        .unwrap();
+    let expected = fs::read_to_string("tests/data/COMMENTS.html").unwrap();
+    let mut dropped = Vec::new();
+    let mut enabled = Vec::new();
+
+    HtmlEncoder::new(mds.clone(), &mut dropped)
+        .encode_html()
+        .unwrap();
+    HtmlEncoder::new(mds, &mut enabled)
+        .enable_comments(true)
+        .encode_html()
+        .unwrap();
+    enabled.push(b'\n');
+    assert_eq!(
+        String::from_utf8(dropped).unwrap(),
+        "<p>Paragraph text [Not a comment]: #</p>\
+         <p>Text after the comment</p>\
+         <blockquote><p>with text</p></blockquote>",
+    );
+    assert_eq!(String::from_utf8(enabled).unwrap(), expected);
+}
+
+#[test]
+fn heading_ids_to_html() {
+    let expected = fs::read_to_string("tests/data/HEADING_IDS.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/HEADING_IDS.md"), expected);
+}
+
+#[test]
+fn captions_to_html() {
+    let expected = fs::read_to_string("tests/data/CAPTIONS.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/CAPTIONS.md"), expected);
+}
+
+#[test]
+fn front_matter_to_html() {
+    let expected = fs::read_to_string("tests/data/FRONT_MATTER.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/FRONT_MATTER.md"), expected);
+}
+
+#[test]
+fn line_breaks_to_html() {
+    let expected = fs::read_to_string("tests/data/LINE_BREAKS.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/LINE_BREAKS.md"), expected);
+}
+
+#[test]
+fn emphasis_to_html() {
+    let expected = fs::read_to_string("tests/data/EMPHASIS.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/EMPHASIS.md"), expected);
+}
+
+#[test]
+fn styles_to_html() {
+    let expected = fs::read_to_string("tests/data/STYLES.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/STYLES.md"), expected);
+}
+
+#[test]
+fn code_spans_to_html() {
+    let expected = fs::read_to_string("tests/data/CODE_SPANS.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/CODE_SPANS.md"), expected);
+}
+
+#[test]
+fn autolinks_to_html() {
+    let expected = fs::read_to_string("tests/data/AUTOLINKS.html").unwrap();
+
+    assert_eq!(file_to_html("tests/data/AUTOLINKS.md"), expected);
+}